@@ -0,0 +1,124 @@
+// Parsing a large getallmanufacturers body and keeping the matches, three ways:
+// a serde_json::Value tree, the owned models, and the streaming parser with borrowed records
+// Run with `cargo bench --bench parse`
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use manufacturers::search::Query;
+use manufacturers::stream::{self, ManufacturerRef};
+use manufacturers::Manufacturer;
+use serde::Deserialize;
+use serde_json::Value;
+
+// Tracks the bytes in use so the peak memory of each path shows up next to its time
+struct Counting;
+
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let in_use = IN_USE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(in_use, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const RECORDS: usize = 20_000;
+const ROUNDS: u32 = 20;
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(rename = "Results")]
+    results: Vec<Manufacturer>,
+}
+
+// A body shaped like vPIC with one BMW every hundred records
+fn body() -> String {
+    let results: Vec<String> = (0..RECORDS)
+        .map(|i| {
+            let (name, common_name, country) = match i % 100 {
+                0 => ("BAYERISCHE MOTOREN WERKE AG", "BMW", "GERMANY"),
+                _ => ("SOME TRAILER COMPANY, LLC", "", "UNITED STATES (USA)"),
+            };
+            format!(
+                r#"{{"Country":"{}","Mfr_CommonName":"{}","Mfr_ID":{},"Mfr_Name":"{} {}","VehicleTypes":[{{"IsPrimary":true,"Name":"Trailer"}},{{"IsPrimary":false,"Name":"Truck "}}]}}"#,
+                country, common_name, i, name, i
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"Count":{},"Message":"Response returned successfully","SearchCriteria":null,"Results":[{}]}}"#,
+        RECORDS,
+        results.join(",")
+    )
+}
+
+// Average time per round and the most memory held on top of the body at once
+fn measure(label: &str, mut f: impl FnMut() -> usize) {
+    let before = IN_USE.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let expected = f();
+    let peak = PEAK.load(Ordering::Relaxed) - before;
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        assert_eq!(black_box(f()), expected);
+    }
+    let elapsed = start.elapsed() / ROUNDS;
+    println!(
+        "{:<10} {:>8.2} ms {:>8} KiB peak, {} kept",
+        label,
+        elapsed.as_secs_f64() * 1000.0,
+        peak / 1024,
+        expected
+    );
+}
+
+fn main() {
+    let body = body();
+    let query = Query::parse("bmw");
+    println!(
+        "{} records, {} KiB body, query \"bmw\"",
+        RECORDS,
+        body.len() / 1024
+    );
+
+    measure("value", || {
+        let tree: Value = serde_json::from_str(&body).unwrap();
+        let results: Vec<Manufacturer> = serde_json::from_value(tree["Results"].clone()).unwrap();
+        results
+            .into_iter()
+            .filter(|m| query.score(m).is_some())
+            .count()
+    });
+    measure("owned", || {
+        let response: Response = serde_json::from_str(&body).unwrap();
+        response
+            .results
+            .into_iter()
+            .filter(|m| query.score(m).is_some())
+            .count()
+    });
+    measure("streaming", || {
+        let (kept, _) =
+            stream::filter_results(&body, |m: &ManufacturerRef| query.score(m).is_some()).unwrap();
+        kept.iter().map(ManufacturerRef::to_manufacturer).count()
+    });
+
+    // Strings without escapes have to come straight out of the body
+    let (first, _) = stream::filter_results(&body, |_: &ManufacturerRef| true).unwrap();
+    assert!(first
+        .iter()
+        .all(|m| matches!(m.name, Some(std::borrow::Cow::Borrowed(_)))));
+}
@@ -0,0 +1,155 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::dirs::app_dir;
+use crate::error::Error;
+use crate::models::Manufacturer;
+use crate::search::fold;
+
+// Legal forms dropped from the end of a name, already normalized
+const LEGAL_SUFFIXES: &str = "ab ag as bv co company corp corporation gmbh inc incorporated kg \
+    limited llc ltd nv oy plc pty sa sas se spa srl";
+
+// Well known manufacturers registered under several legal names
+// Same format as an aliases file: canonical name = other names
+const BUILTIN: &str = "
+BMW = Bayerische Motoren Werke, BMW AG
+Mercedes-Benz = Mercedes Benz, Daimler, Daimler Chrysler, Mercedes-Benz Group
+Volkswagen = VW, Volkswagen AG, Volkswagenwerk
+General Motors = GM, General Motors Company
+Ford = Ford Motor, Ford Motor Company
+Toyota = Toyota Motor, Toyota Motor Corporation
+Honda = Honda Motor, Honda Motor Co
+Tesla = Tesla Motors
+Stellantis = FCA, Fiat Chrysler Automobiles
+";
+
+// Lowercase, without accents, legal suffixes or punctuation
+// "BAYERISCHE MOTOREN WERKE AG" and "Bayerische Motoren-Werke" both give "bayerische motoren werke"
+pub fn normalize(name: &str) -> String {
+    let mut text = String::with_capacity(name.len());
+    for c in fold(name).chars() {
+        match c {
+            // S.A. and O'Brien keep their letters together
+            '.' | '\'' => {}
+            c if c.is_alphanumeric() => text.push(c),
+            _ => text.push(' '),
+        }
+    }
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    // "CO., LTD." drops both, a name made only of a legal form is kept
+    while let [_, .., last] = words[..] {
+        if !LEGAL_SUFFIXES
+            .split_whitespace()
+            .any(|suffix| suffix == last)
+        {
+            break;
+        }
+        words.pop();
+    }
+    words.join(" ")
+}
+
+// Maps the names a manufacturer is known under to one canonical name
+#[derive(Debug, Clone, Default)]
+pub struct Aliases {
+    // normalized name -> canonical name as written in the table
+    canonical: HashMap<String, String>,
+}
+
+impl Aliases {
+    // The built-in table
+    pub fn builtin() -> Aliases {
+        let mut aliases = Aliases::default();
+        // The built-in table is known to parse
+        let _ = aliases.extend_from(BUILTIN);
+        aliases
+    }
+
+    // $XDG_CONFIG_HOME/manufacturers/aliases, falling back to ~/.config
+    pub fn default_path() -> PathBuf {
+        app_dir("XDG_CONFIG_HOME", &[".config"]).join("aliases")
+    }
+
+    // The built-in table with the entries of the file on top
+    pub fn load(path: &Path) -> Result<Aliases, Error> {
+        let content = fs::read_to_string(path).map_err(|source| Error::Io {
+            context: format!("Could not read aliases {}", path.display()),
+            source,
+        })?;
+        let mut aliases = Aliases::builtin();
+        aliases
+            .extend_from(&content)
+            .map_err(|e| Error::InvalidInput(format!("{}: {}", path.display(), e)))?;
+        Ok(aliases)
+    }
+
+    // One `Canonical = alias, alias` per line, blank lines and # comments are skipped
+    fn extend_from(&mut self, table: &str) -> Result<(), String> {
+        for (number, line) in table.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (canonical, names) = line
+                .split_once('=')
+                .map(|(canonical, names)| (canonical.trim(), names))
+                .filter(|(canonical, _)| !canonical.is_empty())
+                .ok_or(format!(
+                    "line {}: expected `Canonical name = alias, alias`",
+                    number + 1
+                ))?;
+            for name in names.split(',').chain([canonical]) {
+                let name = normalize(name);
+                if !name.is_empty() {
+                    self.canonical.insert(name, canonical.to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Canonical name for any of the names in the table
+    pub fn canonical(&self, name: &str) -> Option<&str> {
+        self.canonical.get(&normalize(name)).map(String::as_str)
+    }
+
+    // Every normalized name sharing the canonical name of `name`, itself included
+    // Empty when the name is not in the table
+    pub fn names_like(&self, name: &str) -> Vec<&str> {
+        let Some(canonical) = self.canonical(name) else {
+            return Vec::new();
+        };
+        let names: BTreeSet<&str> = self
+            .canonical
+            .iter()
+            .filter(|(_, other)| other.as_str() == canonical)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.into_iter().collect()
+    }
+
+    // What two records of the same manufacturer have in common: the canonical
+    // name when the legal name is in the table, else the normalized legal name
+    pub fn key(&self, manufacturer: &Manufacturer) -> String {
+        let name = manufacturer.name.as_deref().unwrap_or_default();
+        match self.canonical(name) {
+            Some(canonical) => normalize(canonical),
+            None => normalize(name),
+        }
+    }
+
+    // First record of every manufacturer, the order is kept so the best match stays
+    // Records without a name are never folded together
+    pub fn dedup<'a>(&self, manufacturers: Vec<&'a Manufacturer>) -> Vec<&'a Manufacturer> {
+        let mut seen = BTreeSet::new();
+        manufacturers
+            .into_iter()
+            .filter(|manufacturer| {
+                let key = self.key(manufacturer);
+                key.is_empty() || seen.insert(key)
+            })
+            .collect()
+    }
+}
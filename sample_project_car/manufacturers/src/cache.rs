@@ -0,0 +1,97 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dirs::app_dir;
+
+// How long a cached response is used before asking the server again
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// A stored response body together with what is needed to revalidate it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub url: String,
+    pub fetched_at: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+}
+
+// Response cache on disk, one JSON file per URL
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+// Seconds since the epoch
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+// FNV-1a, unlike DefaultHasher it gives the same file name on every Rust version
+fn hash(url: &str) -> u64 {
+    url.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Cache {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    // $XDG_CACHE_HOME/manufacturers, falling back to ~/.cache/manufacturers
+    pub fn default_dir() -> PathBuf {
+        app_dir("XDG_CACHE_HOME", &[".cache"])
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", hash(url)))
+    }
+
+    // Stored entry for the URL, whatever its age
+    // Unreadable entries are treated as missing and get refetched
+    pub fn load(&self, url: &str) -> Option<Entry> {
+        let content = fs::read_to_string(self.path(url)).ok()?;
+        let entry: Entry = serde_json::from_str(&content).ok()?;
+        (entry.url == url).then_some(entry)
+    }
+
+    pub fn is_fresh(&self, entry: &Entry) -> bool {
+        now().saturating_sub(entry.fetched_at) < self.ttl.as_secs()
+    }
+
+    pub fn store(
+        &self,
+        url: &str,
+        body: String,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    ) -> io::Result<()> {
+        let entry = Entry {
+            url: url.to_string(),
+            fetched_at: now(),
+            etag,
+            last_modified,
+            body,
+        };
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(url), serde_json::to_string(&entry)?)
+    }
+
+    // The server confirmed the entry is unchanged, restart its TTL
+    pub fn touch(&self, mut entry: Entry) -> io::Result<()> {
+        entry.fetched_at = now();
+        fs::write(self.path(&entry.url), serde_json::to_string(&entry)?)
+    }
+}
@@ -0,0 +1,419 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::{
+    HeaderMap, HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+};
+use reqwest::{Request, StatusCode};
+use serde::de::DeserializeOwned;
+use tokio::task::JoinSet;
+
+use crate::cache::Cache;
+use crate::error::Error;
+use crate::models::{
+    DecodedVin, Make, MakeVehicleType, Manufacturer, ManufacturerDetails, Model, Response,
+};
+use crate::retry::{self, RetryPolicy, DEFAULT_CONNECT_TIMEOUT, DEFAULT_TIMEOUT};
+use crate::stream::{self, ManufacturerRef};
+use crate::vin::Vin;
+
+// Where the vPIC vehicle API lives, override it to talk to a mirror or a mock server
+pub const DEFAULT_BASE_URL: &str = "https://vpic.nhtsa.dot.gov/api/vehicles";
+
+// Client for the vPIC vehicle API
+// Cloning is cheap, the underlying reqwest client shares its connection pool
+#[derive(Debug, Clone)]
+pub struct VpicClient {
+    http: reqwest::Client,
+    base_url: String,
+    cache: Option<Cache>,
+    offline: bool,
+    retry: RetryPolicy,
+}
+
+impl Default for VpicClient {
+    fn default() -> Self {
+        VpicClient::new()
+    }
+}
+
+// Percent-encode a value used as a path segment, e.g. "mercedes benz"
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// Unwrap the results from a vPIC response body
+fn parse<T: DeserializeOwned>(url: &str, body: &str) -> Result<Vec<T>, Error> {
+    let res: Response<T> = serde_json::from_str(body).map_err(|source| Error::Decode {
+        url: url.to_string(),
+        source,
+    })?;
+    Ok(res.results)
+}
+
+// vPIC answers in XML unless format=json comes first
+fn json_query<'a>(query: &[(&'a str, String)]) -> Vec<(&'a str, String)> {
+    let mut json = vec![("format", "json".to_string())];
+    json.extend_from_slice(query);
+    json
+}
+
+// Decides while streaming a page which manufacturers are worth copying out of the body
+type Keep = Arc<dyn Fn(&ManufacturerRef) -> bool + Send + Sync>;
+
+// Stream the results of a page through `keep`, returning the kept records and how many there were
+fn parse_filtered(url: &str, body: &str, keep: &Keep) -> Result<(Vec<Manufacturer>, usize), Error> {
+    let (kept, count) =
+        stream::filter_results(body, |manufacturer: &ManufacturerRef| keep(manufacturer)).map_err(
+            |source| Error::Decode {
+                url: url.to_string(),
+                source,
+            },
+        )?;
+    let kept = kept.iter().map(ManufacturerRef::to_manufacturer).collect();
+    Ok((kept, count))
+}
+
+// The response is already in hand, so a cache that cannot be written only costs
+// a refetch next time and is not worth failing the command for
+fn warn_cache(result: std::io::Result<()>) {
+    if let Err(e) = result {
+        eprintln!("Warning: could not write the response cache: {}", e);
+    }
+}
+
+// Header value as an owned string, if present and readable
+fn header(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    let value = headers.get(name)?.to_str().ok()?;
+    Some(value.to_string())
+}
+
+// reqwest client with both timeouts set
+fn http_client(connect: Duration, timeout: Duration) -> Result<reqwest::Client, Error> {
+    let http = reqwest::Client::builder()
+        .connect_timeout(connect)
+        .timeout(timeout)
+        .build()?;
+    Ok(http)
+}
+
+impl VpicClient {
+    // Client for the public vPIC API
+    pub fn new() -> Self {
+        VpicClient::with_base_url(DEFAULT_BASE_URL)
+    }
+
+    // Client for any server exposing the vPIC routes
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        // Like reqwest::Client::new this only fails when TLS cannot be set up at all
+        let http = http_client(DEFAULT_CONNECT_TIMEOUT, DEFAULT_TIMEOUT)
+            .expect("the HTTP client could not be initialised");
+        VpicClient {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            cache: None,
+            offline: false,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    // Give up connecting after `connect` and on a whole response after `timeout`
+    pub fn with_timeouts(mut self, connect: Duration, timeout: Duration) -> Result<Self, Error> {
+        self.http = http_client(connect, timeout)?;
+        Ok(self)
+    }
+
+    // How failed requests are retried, RetryPolicy::none() to fail right away
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    // Keep responses on disk and reuse them while they are fresh
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    // Only answer from the cache, never touching the network
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    // GET {base_url}/{path}?format=json plus the extra query and unwrap the envelope
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<T>, Error> {
+        self.fetch(path, &json_query(query), parse).await
+    }
+
+    // GET {base_url}/{path}?query as a whole JSON document, for APIs other than vPIC
+    // that answer in JSON without being asked and have their own envelope
+    pub(crate) async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, Error> {
+        self.fetch(path, query, |url, body| {
+            serde_json::from_str(body).map_err(|source| Error::Decode {
+                url: url.to_string(),
+                source,
+            })
+        })
+        .await
+    }
+
+    // GET {base_url}/{path}?query and hand the body to `parse`
+    // Goes through the cache when there is one
+    async fn fetch<R>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        parse: impl Fn(&str, &str) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let mut request = self
+            .http
+            .get(format!("{}/{}", self.base_url, path))
+            .query(query)
+            .build()
+            .map_err(|e| Error::InvalidInput(format!("Invalid URL {}: {}", self.base_url, e)))?;
+        let url = request.url().to_string();
+        let cached = self.cache.as_ref().and_then(|cache| cache.load(&url));
+
+        if self.offline {
+            return match cached {
+                Some(entry) => parse(&url, &entry.body),
+                None => Err(Error::NotCached(url)),
+            };
+        }
+
+        // Fresh entries are used as is, stale ones are revalidated with the server
+        if let (Some(cache), Some(entry)) = (&self.cache, &cached) {
+            if cache.is_fresh(entry) {
+                return parse(&url, &entry.body);
+            }
+            // Validators that are no longer valid header values are dropped
+            if let Some(Ok(etag)) = entry.etag.as_deref().map(str::parse) {
+                request.headers_mut().insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(Ok(last_modified)) = entry.last_modified.as_deref().map(str::parse) {
+                request
+                    .headers_mut()
+                    .insert(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let res = self.send(request).await?;
+        if res.status() == StatusCode::NOT_MODIFIED {
+            // Without an entry nothing was revalidated and there is no body to use
+            return match (&self.cache, cached) {
+                (Some(cache), Some(entry)) => {
+                    let results = parse(&url, &entry.body)?;
+                    warn_cache(cache.touch(entry));
+                    Ok(results)
+                }
+                _ => Err(Error::Status {
+                    url,
+                    status: res.status(),
+                }),
+            };
+        }
+
+        let status = res.status();
+        if status.is_client_error() || status.is_server_error() {
+            return Err(Error::Status { url, status });
+        }
+        let etag = header(res.headers(), ETAG);
+        let last_modified = header(res.headers(), LAST_MODIFIED);
+        let body = res.text().await?;
+
+        // Parse before storing so a broken body never ends up in the cache
+        let results = parse(&url, &body)?;
+        if let Some(cache) = &self.cache {
+            warn_cache(cache.store(&url, body, etag, last_modified));
+        }
+        Ok(results)
+    }
+
+    // Send the request, retrying rate limits, server errors, timeouts and refused connections
+    // After the last retry the response or error is handed back as is
+    async fn send(&self, request: Request) -> Result<reqwest::Response, Error> {
+        let mut attempt = 0;
+        loop {
+            // GET requests have no body, so cloning them always works
+            let attempt_request = request
+                .try_clone()
+                .ok_or(Error::Internal("the request cannot be retried".to_string()))?;
+            let result = self.http.execute(attempt_request).await;
+            let retry_after = match &result {
+                Ok(res) if RetryPolicy::is_retryable(res.status()) => {
+                    header(res.headers(), RETRY_AFTER)
+                        .and_then(|value| retry::parse_retry_after(&value))
+                }
+                Err(e) if e.is_timeout() || e.is_connect() => None,
+                _ => return result.map_err(Error::from),
+            };
+            match self.retry.delay(attempt, retry_after) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return result.map_err(Error::from),
+            }
+            attempt += 1;
+        }
+    }
+
+    // One page of getallmanufacturers, pages start at 1
+    pub async fn all_manufacturers(&self, page: usize) -> Result<Vec<Manufacturer>, Error> {
+        self.get("getallmanufacturers", &[("page", page.to_string())])
+            .await
+    }
+
+    // One page of getallmanufacturers keeping only what `keep` accepts, with the page size
+    async fn manufacturers_page(
+        &self,
+        page: usize,
+        keep: Option<Keep>,
+    ) -> Result<(Vec<Manufacturer>, usize), Error> {
+        let query = json_query(&[("page", page.to_string())]);
+        match keep {
+            Some(keep) => {
+                self.fetch("getallmanufacturers", &query, |url, body| {
+                    parse_filtered(url, body, &keep)
+                })
+                .await
+            }
+            None => {
+                let manufacturers = self.all_manufacturers(page).await?;
+                let count = manufacturers.len();
+                Ok((manufacturers, count))
+            }
+        }
+    }
+
+    // Fetch pages `concurrency` at a time until an empty page comes back or max_pages is reached
+    // Reaching max_pages first is warned about on stderr
+    pub async fn all_manufacturers_paged(
+        &self,
+        concurrency: usize,
+        max_pages: usize,
+    ) -> Result<Vec<Manufacturer>, Error> {
+        self.paged(concurrency, max_pages, None).await
+    }
+
+    // Like all_manufacturers_paged, but records are checked while each page is streamed
+    // and only those `keep` accepts are copied, the others never get allocated
+    pub async fn all_manufacturers_filtered(
+        &self,
+        concurrency: usize,
+        max_pages: usize,
+        keep: impl Fn(&ManufacturerRef) -> bool + Send + Sync + 'static,
+    ) -> Result<Vec<Manufacturer>, Error> {
+        self.paged(concurrency, max_pages, Some(Arc::new(keep)))
+            .await
+    }
+
+    async fn paged(
+        &self,
+        concurrency: usize,
+        max_pages: usize,
+        keep: Option<Keep>,
+    ) -> Result<Vec<Manufacturer>, Error> {
+        let mut manufacturers = Vec::new();
+        let mut next_page = 1;
+        while next_page <= max_pages {
+            let last_page = next_page
+                .saturating_add(concurrency.max(1) - 1)
+                .min(max_pages);
+            let mut requests = JoinSet::new();
+            for page in next_page..=last_page {
+                let client = self.clone();
+                let keep = keep.clone();
+                requests.spawn(async move { (page, client.manufacturers_page(page, keep).await) });
+            }
+
+            // Pages can finish in any order, put them back in sequence
+            let mut pages = Vec::new();
+            while let Some(page) = requests.join_next().await {
+                pages.push(page?);
+            }
+            pages.sort_by_key(|(page, _)| *page);
+
+            // Everything after the first empty page is past the end of the data,
+            // so a page there that failed or was never cached does not matter
+            for (_, results) in pages {
+                let (results, count) = results?;
+                if count == 0 {
+                    return Ok(manufacturers);
+                }
+                manufacturers.extend(results);
+            }
+            next_page = last_page + 1;
+        }
+        // Every page up to the cap had records, so there may be more
+        eprintln!(
+            "Warning: stopped after {} pages, later manufacturers are left out",
+            max_pages
+        );
+        Ok(manufacturers)
+    }
+
+    // Full details of every manufacturer matching the name
+    pub async fn manufacturer_details(
+        &self,
+        name: &str,
+    ) -> Result<Vec<ManufacturerDetails>, Error> {
+        let path = format!("GetManufacturerDetails/{}", encode_segment(name));
+        self.get(&path, &[]).await
+    }
+
+    // Makes registered by the manufacturers matching the name
+    pub async fn makes_for_manufacturer(&self, name: &str) -> Result<Vec<Make>, Error> {
+        let path = format!("GetMakeForManufacturer/{}", encode_segment(name));
+        self.get(&path, &[]).await
+    }
+
+    // Models of a make, limited to one model year when given
+    pub async fn models_for_make(
+        &self,
+        make: &str,
+        year: Option<u16>,
+    ) -> Result<Vec<Model>, Error> {
+        let path = match year {
+            Some(year) => format!(
+                "GetModelsForMakeYear/make/{}/modelyear/{}",
+                encode_segment(make),
+                year
+            ),
+            None => format!("GetModelsForMake/{}", encode_segment(make)),
+        };
+        self.get(&path, &[]).await
+    }
+
+    // Vehicle types built under a make, e.g. "Passenger Car"
+    pub async fn vehicle_types_for_make(&self, make: &str) -> Result<Vec<MakeVehicleType>, Error> {
+        let path = format!("GetVehicleTypesForMake/{}", encode_segment(make));
+        self.get(&path, &[]).await
+    }
+
+    // Decode a VIN with vPIC, the VIN type guarantees it was validated locally first
+    pub async fn decode_vin(&self, vin: &Vin) -> Result<Option<DecodedVin>, Error> {
+        let path = format!("DecodeVinValues/{}", vin.as_str());
+        let results = self.get(&path, &[]).await?;
+        Ok(results.into_iter().next())
+    }
+}
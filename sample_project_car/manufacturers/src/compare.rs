@@ -0,0 +1,182 @@
+use std::collections::BTreeSet;
+
+use crate::alias::Aliases;
+use crate::enrich::Enriched;
+use crate::models::{Manufacturer, ManufacturerDetails};
+
+// One line of a comparison, multi-valued fields keep every value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub field: &'static str,
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+}
+
+impl Row {
+    // Lists are compared as sets, their order says nothing
+    pub fn same(&self) -> bool {
+        let left: BTreeSet<&String> = self.left.iter().collect();
+        let right: BTreeSet<&String> = self.right.iter().collect();
+        left == right
+    }
+}
+
+// Two manufacturers field by field
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub left: String,
+    pub right: String,
+    pub rows: Vec<Row>,
+}
+
+// Countries of every record sharing the manufacturer's alias key, e.g. the
+// German and the US registrations of one company
+pub fn registered_countries(
+    aliases: &Aliases,
+    manufacturers: &[Manufacturer],
+    manufacturer: &Manufacturer,
+) -> Vec<String> {
+    let key = aliases.key(manufacturer);
+    let countries: BTreeSet<String> = manufacturers
+        .iter()
+        .filter(|other| {
+            other.id == manufacturer.id || (!key.is_empty() && aliases.key(other) == key)
+        })
+        .filter_map(|other| other.country.clone())
+        .filter(|country| !country.trim().is_empty())
+        .collect();
+    countries.into_iter().collect()
+}
+
+// Present values of a field, empty when vPIC has none
+fn text(value: Option<&str>) -> Vec<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .into_iter()
+        .collect()
+}
+
+fn fields(enriched: &Enriched, countries: &[String]) -> Vec<(&'static str, Vec<String>)> {
+    let manufacturer = &enriched.manufacturer;
+    let details = enriched.details.as_ref();
+    let detail = |value: fn(&ManufacturerDetails) -> Option<&str>| text(details.and_then(value));
+    let mut vehicle_types: Vec<String> = manufacturer
+        .vehicle_types
+        .iter()
+        .filter_map(|vehicle_type| {
+            let name = vehicle_type.name.as_deref()?;
+            Some(match vehicle_type.is_primary {
+                true => format!("{} (primary)", name),
+                false => name.to_string(),
+            })
+        })
+        .collect();
+    vehicle_types.sort();
+    let mut makes: Vec<String> = enriched
+        .makes
+        .iter()
+        .filter_map(|make| make.name.clone())
+        .collect();
+    makes.sort();
+    makes.dedup();
+    vec![
+        ("Name", text(manufacturer.name.as_deref())),
+        ("Common Name", text(manufacturer.common_name.as_deref())),
+        ("Country", text(manufacturer.country.as_deref())),
+        ("City", detail(|details| details.city.as_deref())),
+        (
+            "State/Province",
+            detail(|details| details.state_province.as_deref()),
+        ),
+        ("Vehicle Types", vehicle_types),
+        ("Makes", makes),
+        ("Registered In", countries.to_vec()),
+    ]
+}
+
+impl Comparison {
+    pub fn new(
+        left: &Enriched,
+        left_countries: &[String],
+        right: &Enriched,
+        right_countries: &[String],
+    ) -> Comparison {
+        // Common name when there is one, the legal name is a row anyway
+        let title = |enriched: &Enriched| {
+            let manufacturer = &enriched.manufacturer;
+            let name = manufacturer
+                .common_name
+                .as_deref()
+                .filter(|name| !name.trim().is_empty())
+                .or(manufacturer.name.as_deref())
+                .unwrap_or_default();
+            format!("{} (ID {})", name, manufacturer.id)
+        };
+        let rows = fields(left, left_countries)
+            .into_iter()
+            .zip(fields(right, right_countries))
+            .map(|((field, left), (_, right))| Row { field, left, right })
+            .collect();
+        Comparison {
+            left: title(left),
+            right: title(right),
+            rows,
+        }
+    }
+
+    // Side by side columns, one line per value, rows that differ start with *
+    pub fn render(&self) -> String {
+        let label_width = self
+            .rows
+            .iter()
+            .map(|row| row.field.chars().count())
+            .max()
+            .unwrap_or_default();
+        let left_width = self
+            .rows
+            .iter()
+            .flat_map(|row| &row.left)
+            .chain([&self.left])
+            .map(|value| value.chars().count())
+            .max()
+            .unwrap_or_default();
+        let line = |marker: &str, label: &str, left: &str, right: &str| {
+            format!(
+                "{} {:label_width$}  {:left_width$}  {}",
+                marker, label, left, right
+            )
+            .trim_end()
+            .to_string()
+        };
+
+        let mut lines = vec![line(" ", "", &self.left, &self.right)];
+        for row in &self.rows {
+            let marker = match row.same() {
+                true => " ",
+                false => "*",
+            };
+            let height = row.left.len().max(row.right.len()).max(1);
+            for i in 0..height {
+                let value = |values: &[String]| match values.get(i) {
+                    Some(value) => value.clone(),
+                    None if i == 0 => "-".to_string(),
+                    None => String::new(),
+                };
+                let (marker, label) = match i {
+                    0 => (marker, row.field),
+                    _ => (" ", ""),
+                };
+                lines.push(line(marker, label, &value(&row.left), &value(&row.right)));
+            }
+        }
+        let differences = self.rows.iter().filter(|row| !row.same()).count();
+        lines.push(format!(
+            "{} of {} fields differ (marked *)",
+            differences,
+            self.rows.len()
+        ));
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::dirs::app_dir;
+use crate::error::Error;
+
+// Profile used when neither --profile nor the file picks one
+pub const DEFAULT_PROFILE: &str = "default";
+
+// Settings a profile can give, every one is optional and flags still win
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub base_url: Option<String>,
+    pub safety_url: Option<String>,
+    pub cache_dir: Option<PathBuf>,
+    // Seconds
+    pub cache_ttl: Option<u64>,
+    pub connect_timeout: Option<u64>,
+    pub timeout: Option<u64>,
+    pub retries: Option<u32>,
+    pub concurrency: Option<usize>,
+    pub snapshot: Option<PathBuf>,
+    pub aliases: Option<PathBuf>,
+    // text, json, ndjson, csv or table
+    pub output: Option<String>,
+    // Fields searched by terms without a prefix, e.g. ["name", "common_name"]
+    pub fields: Option<Vec<String>>,
+}
+
+impl Settings {
+    // These settings with the ones given in `over` replacing them
+    fn merge(self, over: Settings) -> Settings {
+        Settings {
+            base_url: over.base_url.or(self.base_url),
+            safety_url: over.safety_url.or(self.safety_url),
+            cache_dir: over.cache_dir.or(self.cache_dir),
+            cache_ttl: over.cache_ttl.or(self.cache_ttl),
+            connect_timeout: over.connect_timeout.or(self.connect_timeout),
+            timeout: over.timeout.or(self.timeout),
+            retries: over.retries.or(self.retries),
+            concurrency: over.concurrency.or(self.concurrency),
+            snapshot: over.snapshot.or(self.snapshot),
+            aliases: over.aliases.or(self.aliases),
+            output: over.output.or(self.output),
+            fields: over.fields.or(self.fields),
+        }
+    }
+}
+
+// The config file, e.g.
+//
+//     profile = "mirror"
+//
+//     [profiles.default]
+//     timeout = 60
+//     output = "table"
+//
+//     [profiles.mirror]
+//     base_url = "http://localhost:8080/api/vehicles"
+//
+// The default profile applies to every run, the selected one on top of it
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+impl Config {
+    // $XDG_CONFIG_HOME/manufacturers/config.toml, falling back to ~/.config
+    pub fn default_path() -> PathBuf {
+        app_dir("XDG_CONFIG_HOME", &[".config"]).join("config.toml")
+    }
+
+    pub fn load(path: &Path) -> Result<Config, Error> {
+        let content = fs::read_to_string(path).map_err(|source| Error::Io {
+            context: format!("Could not read config {}", path.display()),
+            source,
+        })?;
+        Config::parse(&content)
+            .map_err(|e| Error::InvalidInput(format!("Invalid config {}: {}", path.display(), e)))
+    }
+
+    pub fn parse(content: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    // Settings of the profile, `name` overrides the profile picked in the file
+    // Asking for a profile the file does not have is an error, the default one may be missing
+    pub fn settings(&self, name: Option<&str>) -> Result<Settings, Error> {
+        let base = self
+            .profiles
+            .get(DEFAULT_PROFILE)
+            .cloned()
+            .unwrap_or_default();
+        let name = name.or(self.profile.as_deref()).unwrap_or(DEFAULT_PROFILE);
+        if name == DEFAULT_PROFILE {
+            return Ok(base);
+        }
+        match self.profiles.get(name) {
+            Some(profile) => Ok(base.merge(profile.clone())),
+            None => {
+                let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                Err(Error::Usage(format!(
+                    "Unknown profile {}, the config has: {}",
+                    name,
+                    match known.is_empty() {
+                        true => "no profiles".to_string(),
+                        false => known.join(", "),
+                    }
+                )))
+            }
+        }
+    }
+}
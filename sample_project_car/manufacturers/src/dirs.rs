@@ -0,0 +1,14 @@
+use std::env;
+use std::path::PathBuf;
+
+// Directory of the tool under the XDG base directory `var`, e.g. $XDG_CONFIG_HOME/manufacturers
+// Without it the usual default below $HOME is used, e.g. [".config"]
+pub(crate) fn app_dir(var: &str, default: &[&str]) -> PathBuf {
+    let mut dir = match (env::var_os(var), env::var_os("HOME")) {
+        (Some(dir), _) => PathBuf::from(dir),
+        (None, Some(home)) => PathBuf::from(home).join(default.iter().collect::<PathBuf>()),
+        (None, None) => default.iter().collect(),
+    };
+    dir.push("manufacturers");
+    dir
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::client::VpicClient;
+use crate::error::Error;
+use crate::models::{Make, Manufacturer, ManufacturerDetails};
+
+// A search result with its makes and contact details looked up
+#[derive(Debug, Clone)]
+pub struct Enriched {
+    pub manufacturer: Manufacturer,
+    // None when vPIC has no details record for the ID
+    pub details: Option<ManufacturerDetails>,
+    pub makes: Vec<Make>,
+}
+
+impl Enriched {
+    pub fn description(&self) -> String {
+        let mut lines = vec![self.manufacturer.description()];
+        if let Some(details) = &self.details {
+            let details = details.description();
+            if !details.is_empty() {
+                lines.push(details);
+            }
+        }
+        let makes: Vec<&str> = self
+            .makes
+            .iter()
+            .filter_map(|make| make.name.as_deref())
+            .collect();
+        lines.push(format!("\tMakes: {}", makes.join(", ")));
+        lines.join("\n")
+    }
+}
+
+// Look up the details and makes of every manufacturer, results keep their order
+// Both lookups of all manufacturers run at once, at most `concurrency` requests
+// in flight, the first failure ends the enrichment
+pub async fn enrich(
+    client: &VpicClient,
+    manufacturers: Vec<Manufacturer>,
+    concurrency: usize,
+) -> Result<Vec<Enriched>, Error> {
+    let permits = Arc::new(Semaphore::new(concurrency.clamp(1, Semaphore::MAX_PERMITS)));
+    let mut lookups = JoinSet::new();
+    for (position, manufacturer) in manufacturers.into_iter().enumerate() {
+        let client = client.clone();
+        let permits = permits.clone();
+        lookups.spawn(async move {
+            let enriched = lookup(&client, &permits, manufacturer).await;
+            (position, enriched)
+        });
+    }
+
+    let mut enriched = Vec::new();
+    while let Some(lookup) = lookups.join_next().await {
+        let (position, result) = lookup?;
+        enriched.push((position, result?));
+    }
+    enriched.sort_by_key(|(position, _)| *position);
+    Ok(enriched.into_iter().map(|(_, enriched)| enriched).collect())
+}
+
+// Both endpoints accept the manufacturer ID, which unlike the name is never ambiguous
+async fn lookup(
+    client: &VpicClient,
+    permits: &Semaphore,
+    manufacturer: Manufacturer,
+) -> Result<Enriched, Error> {
+    let id = manufacturer.id.to_string();
+    let details = async {
+        let _permit = acquire(permits).await?;
+        client.manufacturer_details(&id).await
+    };
+    let makes = async {
+        let _permit = acquire(permits).await?;
+        client.makes_for_manufacturer(&id).await
+    };
+    let (details, makes) = tokio::try_join!(details, makes)?;
+    Ok(Enriched {
+        details: details
+            .into_iter()
+            .find(|details| details.id == manufacturer.id),
+        manufacturer,
+        makes,
+    })
+}
+
+async fn acquire(permits: &Semaphore) -> Result<tokio::sync::SemaphorePermit<'_>, Error> {
+    // The semaphore is never closed
+    permits
+        .acquire()
+        .await
+        .map_err(|e| Error::Internal(e.to_string()))
+}
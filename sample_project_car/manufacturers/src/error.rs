@@ -0,0 +1,101 @@
+use std::fmt;
+use std::io;
+
+use reqwest::StatusCode;
+
+// Everything that can go wrong, each kind has its own exit code
+#[derive(Debug)]
+pub enum Error {
+    // A search or lookup that worked but matched nothing
+    NoResults(String),
+    // Bad arguments on the command line
+    Usage(String),
+    // The server could not be reached or the connection broke
+    Network(reqwest::Error),
+    // The server answered with a 4xx or 5xx status, or a 304 for nothing cached
+    Status {
+        url: String,
+        status: StatusCode,
+    },
+    // The body was not the JSON we expected
+    Decode {
+        url: String,
+        source: serde_json::Error,
+    },
+    // --offline and the response was never cached
+    NotCached(String),
+    // Reading or writing a file failed
+    Io {
+        context: String,
+        source: io::Error,
+    },
+    // A VIN, URL or other value that does not hold up
+    InvalidInput(String),
+    // A bug, such as a fetch task that panicked
+    Internal(String),
+}
+
+impl Error {
+    // Process exit code, documented in the usage text
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::NoResults(_) => 1,
+            Error::Usage(_) => 2,
+            Error::Network(_) => 3,
+            Error::Status { .. } => 4,
+            Error::Decode { .. } => 5,
+            Error::NotCached(_) => 6,
+            Error::Io { .. } => 7,
+            Error::InvalidInput(_) => 8,
+            Error::Internal(_) => 70,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoResults(message) | Error::Usage(message) | Error::InvalidInput(message) => {
+                write!(f, "{}", message)
+            }
+            Error::Network(e) if e.is_timeout() => write!(f, "The request timed out: {}", e),
+            // vPIC and the NHTSA safety API live on different hosts
+            Error::Network(e) => match e.url().and_then(|url| url.host_str()) {
+                Some(host) => write!(f, "Could not reach {}: {}", host, e),
+                None => write!(f, "Could not reach the server: {}", e),
+            },
+            Error::Status { url, status } => write!(f, "{} answered {}", url, status),
+            Error::Decode { url, source } => {
+                write!(f, "Unexpected response from {}: {}", url, source)
+            }
+            Error::NotCached(url) => {
+                write!(f, "{} is not cached, run once without --offline", url)
+            }
+            Error::Io { context, source } => write!(f, "{}: {}", context, source),
+            Error::Internal(message) => write!(f, "Internal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network(e) => Some(e),
+            Error::Decode { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Network(e)
+    }
+}
+
+impl From<tokio::task::JoinError> for Error {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Error::Internal(e.to_string())
+    }
+}
@@ -0,0 +1,27 @@
+// Library side of the manufacturers tool
+// Holds the vPIC models and client so other tools can reuse them
+#![deny(clippy::all)]
+
+pub mod alias;
+pub mod cache;
+pub mod client;
+pub mod compare;
+pub mod config;
+mod dirs;
+pub mod enrich;
+pub mod error;
+pub mod models;
+pub mod output;
+pub mod report;
+pub mod retry;
+pub mod safety;
+pub mod search;
+pub mod snapshot;
+pub mod stream;
+pub mod vin;
+
+pub use cache::Cache;
+pub use client::VpicClient;
+pub use error::Error;
+pub use models::Manufacturer;
+pub use vin::Vin;
@@ -1,109 +1,1184 @@
-// Added crartes to Cargo.toml: serde_json, reqwest, tokio
+// Added crartes to Cargo.toml: serde, serde_json, reqwest, tokio
 // Add clippy an run cargo watch with an argument BMW
 // > cargo-watch -qc -x 'run -- BMW' -x clippy
 // We can change the model to any other
+// Decode a VIN with > cargo run -- decode-vin 1HGCM82633A004352
+// Check a spreadsheet of VINs offline with > cargo run -- validate-vins vins.csv
+// Browse with > cargo run -- makes --manufacturer BMW, or models --make BMW --year 2020
+// Run a search per line of a file with > cargo run -- --input keywords.txt
+// Search interactively without refetching with > cargo run -- repl
+// Count manufacturers per country with > cargo run -- report --group-by country --top 10
+// Look up safety recalls with > cargo run -- recalls --make honda --model accord --year 2003
+// Put two manufacturers side by side with > cargo run -- compare bmw "mercedes benz"
+// Keep settings per server in ~/.config/manufacturers/config.toml, pick one with --profile
+// Mirror everything locally with > cargo run -- sync, later searches use the snapshot
 #![deny(clippy::all)]
+
+// print! and println! panic when stdout is a pipe closed early, as with `| head`
+// These shadow them for the whole binary and end the program quietly instead
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::write_stdout(format_args!($($arg)*))
+    };
+}
+
+macro_rules! println {
+    () => {
+        print!("\n")
+    };
+    ($($arg:tt)*) => {
+        print!("{}\n", format_args!($($arg)*))
+    };
+}
+
+mod repl;
+
 use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+use std::str::FromStr;
+use std::time::Duration;
+
+use manufacturers::alias::Aliases;
+use manufacturers::cache::DEFAULT_TTL;
+use manufacturers::client::DEFAULT_BASE_URL;
+use manufacturers::compare::{self, Comparison};
+use manufacturers::config::{Config, Settings};
+use manufacturers::enrich;
+use manufacturers::output::{self, DiffEntry, Format, QueryHit, Record, VinCheck, VinDecoding};
+use manufacturers::report::{self, GroupBy};
+use manufacturers::retry::{RetryPolicy, DEFAULT_CONNECT_TIMEOUT, DEFAULT_TIMEOUT};
+use manufacturers::safety::{
+    FixtureSource, NhtsaSource, Vehicle, VehicleDataSource, DEFAULT_SAFETY_URL,
+};
+use manufacturers::search::{Field, Index, Query};
+use manufacturers::snapshot::{self, Snapshot};
+use manufacturers::vin;
+use manufacturers::{Cache, Error, Manufacturer, Vin, VpicClient};
+
+// Defaults for how many pages are fetched at once and in total
+const DEFAULT_CONCURRENCY: usize = 4;
+const DEFAULT_MAX_PAGES: usize = 200;
+
+// Subcommands, any other first word starts a search
+const COMMANDS: &[&str] = &[
+    "decode-vin",
+    "validate-vins",
+    "makes",
+    "models",
+    "vehicle-types",
+    "recalls",
+    "complaints",
+    "sync",
+    "diff",
+    "report",
+    "compare",
+    "repl",
+];
+
+const USAGE: &str = "Usage: manufacturers <search query> [options]
+       manufacturers --input <FILE|-> [options]
+       manufacturers decode-vin <VIN> [options]
+       manufacturers validate-vins <FILE|-> [--skip-check-digit] [options]
+       manufacturers makes --manufacturer <NAME> [options]
+       manufacturers models --make <MAKE> [--year YEAR] [options]
+       manufacturers vehicle-types --make <MAKE> [options]
+       manufacturers recalls --make <MAKE> --model <MODEL> --year <YEAR> [options]
+       manufacturers complaints --make <MAKE> --model <MODEL> --year <YEAR> [options]
+       manufacturers report [search query] [--group-by country|vehicle-type] [--top N]
+       manufacturers compare <A> <B> [options]
+       manufacturers repl [options]
+       manufacturers sync [--snapshot FILE] [options]
+       manufacturers diff <OLD SNAPSHOT> <NEW SNAPSHOT> [options]
+
+Options:
+  --config FILE              Config file, see below
+  --profile NAME             Settings of this profile of the config file
+  --concurrency N            Pages or lookups fetched at once
+  --max-pages N              Stop after this many pages
+  --base-url URL             vPIC API base URL
+  --safety-url URL           NHTSA recalls and complaints API base URL
+  --data-dir DIR             Read recalls and complaints from saved responses in DIR
+  --cache-dir DIR            Where responses are cached
+  --cache-ttl SECONDS        How long cached responses are used
+  --no-cache                 Always download
+  --offline                  Only use cached responses
+  --snapshot FILE            Snapshot written by sync and searched, see below
+  --live                     Search the API even when a snapshot exists
+  --connect-timeout SECONDS  Give up connecting after this long
+  --timeout SECONDS          Give up on a response after this long
+  --retries N                Retries for 429, 5xx and timeouts, 0 disables
+  --skip-check-digit         Accept VINs whose 9th character is not a check digit
+  --input FILE               Search every line of the file, - for stdin
+  --output FORMAT            text, json, ndjson, csv or table
+  --columns A,B              Columns for structured output, e.g. id,name,country
+  --group-by GROUP           Report per country (default) or vehicle-type
+  --top N                    Report the N largest groups, the rest as one line
+  --aliases FILE             Extra alias table, see below
+  --all-names                List every legal name instead of one per manufacturer
+  --enrich                   Look up the makes and contact details of every match
+
+Search queries match name, common name and country, ignoring case and accents,
+and tolerate small typos. Scope a term with name:, common:, country: or type:,
+e.g. country:germany name:\"motoren werke\"
+
+Names are compared without punctuation or legal forms such as GmbH, Inc or LLC,
+and a manufacturer registered under several names is listed once. An alias file
+(by default ~/.config/manufacturers/aliases) adds names, one manufacturer per line:
+  BMW = Bayerische Motoren Werke, BMW AG
+
+Defaults come from ~/.config/manufacturers/config.toml when it exists, options
+given on the command line win. The default profile always applies, another one
+selected with --profile or `profile = \"NAME\"` is applied on top of it:
+  [profiles.default]
+  timeout = 60
+  output = \"table\"
+  fields = [\"name\", \"common_name\"]
+  [profiles.mirror]
+  base_url = \"http://localhost:8080/api/vehicles\"
+Profiles can also set safety_url, cache_dir, cache_ttl, connect_timeout,
+retries, concurrency, snapshot and aliases.
+
+compare takes a manufacturer ID or a search query for each side, quote queries
+with spaces, and shows the best match of each with its makes next to each other.
+
+Once sync has saved a snapshot (by default in ~/.local/share/manufacturers),
+searches against the same base URL run against it without downloading anything,
+and decode-vin and validate-vins list the manufacturer records of each VIN's make.
+A snapshot named by --snapshot or the config is used whatever the base URL.
+
+Exit codes:
+  0   Success
+  1   Nothing found
+  2   Invalid arguments
+  3   Network failure or timeout
+  4   The API answered with an HTTP error
+  5   The API answered with unexpected JSON, or a snapshot is damaged
+  6   Not cached while --offline
+  7   Reading input or writing the cache failed
+  8   Invalid VIN or input value
+  70  Internal error";
+
+// Settings shared by every command
+struct Options {
+    concurrency: usize,
+    max_pages: usize,
+    base_url: String,
+    cache_dir: PathBuf,
+    cache_ttl: Duration,
+    use_cache: bool,
+    offline: bool,
+    snapshot: Option<PathBuf>,
+    live: bool,
+    connect_timeout: Duration,
+    timeout: Duration,
+    retries: u32,
+    skip_check_digit: bool,
+    manufacturer: Option<String>,
+    make: Option<String>,
+    model: Option<String>,
+    year: Option<u16>,
+    safety_url: String,
+    data_dir: Option<PathBuf>,
+    input: Option<String>,
+    output: Format,
+    columns: Option<String>,
+    group_by: GroupBy,
+    top: Option<usize>,
+    aliases: Option<PathBuf>,
+    all_names: bool,
+    enrich: bool,
+    fields: Vec<Field>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            concurrency: DEFAULT_CONCURRENCY,
+            max_pages: DEFAULT_MAX_PAGES,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            cache_dir: Cache::default_dir(),
+            cache_ttl: DEFAULT_TTL,
+            use_cache: true,
+            offline: false,
+            snapshot: None,
+            live: false,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            timeout: DEFAULT_TIMEOUT,
+            retries: RetryPolicy::default().max_retries,
+            skip_check_digit: false,
+            manufacturer: None,
+            make: None,
+            model: None,
+            year: None,
+            safety_url: DEFAULT_SAFETY_URL.to_string(),
+            data_dir: None,
+            input: None,
+            output: Format::Text,
+            columns: None,
+            group_by: GroupBy::Country,
+            top: None,
+            aliases: None,
+            all_names: false,
+            enrich: false,
+            fields: Vec::new(),
+        }
+    }
+}
+
+// Read the value following a flag such as "--base-url URL"
+fn flag_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, Error> {
+    args.next()
+        .ok_or(Error::Usage(format!("Missing value for {}", flag)))
+}
+
+// Read a number following a flag such as "--retries 0"
+// Parsed straight into the type it is stored in, so out of range values are rejected
+fn flag_count<T: FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<T, Error> {
+    let value = flag_value(args, flag)?;
+    value
+        .parse()
+        .map_err(|_| Error::Usage(format!("Invalid value for {}: {}", flag, value)))
+}
+
+// Read a positive number following a flag such as "--max-pages 10"
+fn flag_number<T: FromStr + From<u8> + PartialOrd>(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<T, Error> {
+    at_least_one(flag, flag_count(args, flag)?).map_err(Error::Usage)
+}
+
+// Counts, sizes and durations of zero make no sense, whether given as flag or config
+fn at_least_one<T: From<u8> + PartialOrd>(name: &str, value: T) -> Result<T, String> {
+    match value < T::from(1) {
+        true => Err(format!("{} must be at least 1", name)),
+        false => Ok(value),
+    }
+}
+
+impl Options {
+    // Split the arguments into options and positional arguments
+    // Flags can come before or after the positional ones
+    fn parse(args: impl Iterator<Item = String>) -> Result<(Options, Vec<String>), Error> {
+        let args: Vec<String> = args.collect();
+        let mut options = Options::configured(&args)?;
+        let mut positional = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                // Already applied by Options::configured
+                "--config" | "--profile" => {
+                    flag_value(&mut args, &arg)?;
+                }
+                "--concurrency" => options.concurrency = flag_number(&mut args, &arg)?,
+                "--max-pages" => options.max_pages = flag_number(&mut args, &arg)?,
+                "--base-url" => options.base_url = flag_value(&mut args, &arg)?,
+                "--safety-url" => options.safety_url = flag_value(&mut args, &arg)?,
+                "--data-dir" => options.data_dir = Some(flag_value(&mut args, &arg)?.into()),
+                "--cache-dir" => options.cache_dir = flag_value(&mut args, &arg)?.into(),
+                "--cache-ttl" => {
+                    options.cache_ttl = Duration::from_secs(flag_number(&mut args, &arg)?)
+                }
+                "--no-cache" => options.use_cache = false,
+                "--offline" => options.offline = true,
+                "--snapshot" => options.snapshot = Some(flag_value(&mut args, &arg)?.into()),
+                "--live" => options.live = true,
+                "--connect-timeout" => {
+                    options.connect_timeout = Duration::from_secs(flag_number(&mut args, &arg)?)
+                }
+                "--timeout" => options.timeout = Duration::from_secs(flag_number(&mut args, &arg)?),
+                "--retries" => options.retries = flag_count(&mut args, &arg)?,
+                "--skip-check-digit" => options.skip_check_digit = true,
+                "--manufacturer" => options.manufacturer = Some(flag_value(&mut args, &arg)?),
+                "--make" => options.make = Some(flag_value(&mut args, &arg)?),
+                "--model" => options.model = Some(flag_value(&mut args, &arg)?),
+                "--year" => options.year = Some(flag_number(&mut args, &arg)?),
+                "--input" => options.input = Some(flag_value(&mut args, &arg)?),
+                "--output" => {
+                    options.output = flag_value(&mut args, &arg)?.parse().map_err(Error::Usage)?
+                }
+                "--columns" => options.columns = Some(flag_value(&mut args, &arg)?),
+                "--group-by" => {
+                    options.group_by = flag_value(&mut args, &arg)?.parse().map_err(Error::Usage)?
+                }
+                "--top" => options.top = Some(flag_number(&mut args, &arg)?),
+                "--aliases" => options.aliases = Some(flag_value(&mut args, &arg)?.into()),
+                "--all-names" => options.all_names = true,
+                "--enrich" => options.enrich = true,
+                flag if flag.starts_with("--") => {
+                    Err(Error::Usage(format!("Unknown option {}", flag)))?
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        if options.offline && !options.use_cache {
+            Err(Error::Usage(
+                "--offline needs the cache, drop --no-cache".to_string(),
+            ))?;
+        }
+        if options.columns.is_some() && options.output == Format::Text {
+            Err(Error::Usage(
+                "--columns needs a structured --output, e.g. --output csv".to_string(),
+            ))?;
+        }
+        Ok((options, positional))
+    }
+
+    // Defaults with the config file applied, looked up before the other flags so they win
+    // A --config given explicitly has to exist, the default file is optional
+    fn configured(args: &[String]) -> Result<Options, Error> {
+        let value = |flag: &str| -> Result<Option<String>, Error> {
+            match args.iter().position(|arg| arg == flag) {
+                Some(position) => match args.get(position + 1) {
+                    Some(value) => Ok(Some(value.clone())),
+                    None => Err(Error::Usage(format!("Missing value for {}", flag))),
+                },
+                None => Ok(None),
+            }
+        };
+        let profile = value("--profile")?;
+        let config = match value("--config")? {
+            Some(path) => Config::load(Path::new(&path))?,
+            None if Config::default_path().exists() => Config::load(&Config::default_path())?,
+            None => Config::default(),
+        };
+
+        let mut options = Options::default();
+        options.apply(config.settings(profile.as_deref())?)?;
+        Ok(options)
+    }
+
+    // Use the settings of a config profile, the values are checked like the flags they stand for
+    fn apply(&mut self, settings: Settings) -> Result<(), Error> {
+        let invalid = |e: String| Error::InvalidInput(format!("Invalid config: {}", e));
+        if let Some(base_url) = settings.base_url {
+            self.base_url = base_url;
+        }
+        if let Some(safety_url) = settings.safety_url {
+            self.safety_url = safety_url;
+        }
+        if let Some(cache_dir) = settings.cache_dir {
+            self.cache_dir = cache_dir;
+        }
+        if let Some(cache_ttl) = settings.cache_ttl {
+            self.cache_ttl =
+                Duration::from_secs(at_least_one("cache_ttl", cache_ttl).map_err(invalid)?);
+        }
+        if let Some(connect_timeout) = settings.connect_timeout {
+            self.connect_timeout = Duration::from_secs(
+                at_least_one("connect_timeout", connect_timeout).map_err(invalid)?,
+            );
+        }
+        if let Some(timeout) = settings.timeout {
+            self.timeout = Duration::from_secs(at_least_one("timeout", timeout).map_err(invalid)?);
+        }
+        if let Some(retries) = settings.retries {
+            self.retries = retries;
+        }
+        if let Some(concurrency) = settings.concurrency {
+            self.concurrency = at_least_one("concurrency", concurrency).map_err(invalid)?;
+        }
+        if settings.snapshot.is_some() {
+            self.snapshot = settings.snapshot;
+        }
+        if settings.aliases.is_some() {
+            self.aliases = settings.aliases;
+        }
+        if let Some(output) = settings.output {
+            self.output = output.parse().map_err(invalid)?;
+        }
+        if let Some(fields) = settings.fields {
+            self.fields = fields
+                .iter()
+                .map(|field| field.parse())
+                .collect::<Result<_, _>>()
+                .map_err(invalid)?;
+        }
+        Ok(())
+    }
+
+    // A search query as configured: aliases and the default fields applied
+    fn query(&self, input: &str, aliases: &Aliases) -> Query {
+        Query::parse(input)
+            .with_aliases(aliases)
+            .with_fields(&self.fields)
+    }
+
+    // Create a vPIC client, responses are cached on disk so repeated searches skip the download
+    fn client(&self) -> Result<VpicClient, Error> {
+        self.cached_client(&self.base_url)
+    }
+
+    // Same settings as the vPIC client, for the NHTSA safety API
+    fn safety_client(&self) -> Result<VpicClient, Error> {
+        self.cached_client(&self.safety_url)
+    }
+
+    fn cached_client(&self, base_url: &str) -> Result<VpicClient, Error> {
+        let client = self.client_for(base_url)?.offline(self.offline);
+        Ok(match self.use_cache {
+            true => client.with_cache(Cache::new(&self.cache_dir, self.cache_ttl)),
+            false => client,
+        })
+    }
+
+    // A client that always downloads, for sync
+    fn live_client(&self) -> Result<VpicClient, Error> {
+        self.client_for(&self.base_url)
+    }
+
+    fn client_for(&self, base_url: &str) -> Result<VpicClient, Error> {
+        Ok(VpicClient::with_base_url(base_url)
+            .with_timeouts(self.connect_timeout, self.timeout)?
+            .with_retry(RetryPolicy {
+                max_retries: self.retries,
+                ..RetryPolicy::default()
+            }))
+    }
+
+    fn snapshot_path(&self) -> PathBuf {
+        self.snapshot.clone().unwrap_or_else(Snapshot::default_path)
+    }
+
+    // The snapshot to search instead of the API, if any
+    // One named by --snapshot or the config has to exist, the default one is only
+    // used when it was synced from the base URL in effect, so another --base-url
+    // or profile goes to its own server
+    fn snapshot(&self) -> Result<Option<Snapshot>, Error> {
+        if self.live {
+            return Ok(None);
+        }
+        if let Some(path) = &self.snapshot {
+            return Snapshot::load(path).map(Some);
+        }
+        let path = Snapshot::default_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let snapshot = Snapshot::load(&path)?;
+        Ok(Some(snapshot).filter(|snapshot| snapshot.source == self.base_url))
+    }
+
+    // The built-in aliases plus --aliases or the default file when there is one
+    fn aliases(&self) -> Result<Aliases, Error> {
+        match &self.aliases {
+            Some(path) => Aliases::load(path),
+            None if Aliases::default_path().exists() => Aliases::load(&Aliases::default_path()),
+            None => Ok(Aliases::builtin()),
+        }
+    }
 
-// Define API URL
-const API_URL: &str = "https://vpic.nhtsa.dot.gov/api/vehicles/getallmanufacturers?format=json";
+    // Ranked matches, one record per manufacturer unless --all-names
+    fn matches<'a>(
+        &self,
+        aliases: &Aliases,
+        index: &Index,
+        query: &Query,
+        manufacturers: &'a [Manufacturer],
+    ) -> Vec<&'a Manufacturer> {
+        let found = index
+            .rank(query, manufacturers)
+            .into_iter()
+            .map(|(manufacturer, _)| manufacturer)
+            .collect();
+        match self.all_names {
+            true => found,
+            false => aliases.dedup(found),
+        }
+    }
+}
 
-// Define manufacturers struct with a lifetime specifier
-struct Manufacturer<'a> {
-    name: Option<&'a str>,
-    common_name: Option<&'a str>,
-    country: Option<&'a str>,
+// Every manufacturer, from the snapshot when there is one, else from the API
+async fn all_manufacturers(options: &Options) -> Result<Vec<Manufacturer>, Error> {
+    if let Some(snapshot) = options.snapshot()? {
+        return Ok(searched(snapshot));
+    }
+
+    // Every page is deserialized into the typed models
+    // A malformed response is returned as Error::Decode instead of panicking
+    options
+        .client()?
+        .all_manufacturers_paged(options.concurrency, options.max_pages)
+        .await
 }
 
-// Create contains trait
-trait Contains {
-    fn contains(&self, needle: &str) -> bool;
+// Records of the snapshot, saying on stderr where they come from so no format is broken
+fn searched(snapshot: Snapshot) -> Vec<Manufacturer> {
+    eprintln!(
+        "Using the snapshot of {} from {}, pass --live to search the API",
+        snapshot.source,
+        ago(snapshot.age())
+    );
+    snapshot.manufacturers
 }
 
-// Making sure every manuf has a function contains and look for string slice with the fields if they have default values
-impl<'a> Contains for Manufacturer<'a> {
-    fn contains(&self, needle: &str) -> bool {
-        self.name.unwrap_or_default().contains(needle)
-            || self.common_name.unwrap_or_default().contains(needle)
-            || self.country.unwrap_or_default().contains(needle)
+// "3 days ago" style age of a snapshot
+fn ago(age: Duration) -> String {
+    let seconds = age.as_secs();
+    let (count, unit) = match seconds {
+        0..=59 => (seconds, "second"),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    match count {
+        1 => format!("1 {} ago", unit),
+        _ => format!("{} {}s ago", count, unit),
     }
 }
 
-// Implement a description for Manufacturer
-impl<'a> Manufacturer<'a> {
-    fn description(&self) -> String {
-        let name = self.name.unwrap_or_default();
-        let common_name = self.common_name.unwrap_or_default();
-        let country = self.country.unwrap_or_default();
+// Download every page into the snapshot file
+async fn sync(options: &Options) -> Result<(), Error> {
+    if options.offline {
+        Err(Error::Usage(
+            "sync has to download, drop --offline".to_string(),
+        ))?;
+    }
+    let manufacturers = options
+        .live_client()?
+        .all_manufacturers_paged(options.concurrency, options.max_pages)
+        .await?;
+    if manufacturers.is_empty() {
+        Err(Error::NoResults(
+            "The API returned no manufacturers, keeping the old snapshot".to_string(),
+        ))?;
+    }
+
+    let path = options.snapshot_path();
+    let count = manufacturers.len();
+    Snapshot::new(&options.base_url, manufacturers).save(&path)?;
+    println!("Saved {} manufacturers to {}", count, path.display());
+    Ok(())
+}
+
+// Manufacturers added, removed or changed between two snapshots
+fn diff(options: &Options, old: &str, new: &str) -> Result<(), Error> {
+    let old = Snapshot::load(Path::new(old))?;
+    let new = Snapshot::load(Path::new(new))?;
+    let diff = snapshot::diff(&old, &new);
+    if options.output != Format::Text {
+        let entry = |change, manufacturer, fields| DiffEntry {
+            change,
+            manufacturer,
+            fields,
+        };
+        let entries: Vec<DiffEntry> = (diff.added.iter())
+            .map(|manufacturer| entry("added", *manufacturer, Vec::new()))
+            .chain(
+                (diff.removed.iter())
+                    .map(|manufacturer| entry("removed", *manufacturer, Vec::new())),
+            )
+            .chain(diff.changed.iter().map(|(before, after)| {
+                entry("changed", *after, output::changed_fields(before, after))
+            }))
+            .collect();
+        return print_structured(options, &entries);
+    }
+    if diff.is_empty() {
+        println!("No differences");
+        return Ok(());
+    }
+
+    let summary = |manufacturer: &Manufacturer| {
         format!(
-            "\tName: {}\n\tCommon Name: {}\n\tCountry: {}",
-            name, common_name, country,
+            "{} {} ({})",
+            manufacturer.id,
+            manufacturer.name.as_deref().unwrap_or_default(),
+            manufacturer.country.as_deref().unwrap_or("unknown country")
         )
+    };
+    println!("Added ({}):", diff.added.len());
+    for manufacturer in &diff.added {
+        println!("\t+ {}", summary(manufacturer));
     }
+    println!("Removed ({}):", diff.removed.len());
+    for manufacturer in &diff.removed {
+        println!("\t- {}", summary(manufacturer));
+    }
+    println!("Changed ({}):", diff.changed.len());
+    for (before, after) in &diff.changed {
+        println!("\t~ {}", summary(after));
+        for (column, was, is) in output::changed_fields(before, after) {
+            println!("\t\t{}: {} -> {}", column, was, is);
+        }
+    }
+    Ok(())
 }
 
-// Turn main fn async with tokio
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Read all arguments, abort if less than 2 args are passed
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        println!("Usage: {} <search term>", args[0]);
+// Search the query in every manufacturer, e.g. `bmw` or `country:germany name:motor`
+async fn search(options: &Options, query: &str) -> Result<(), Error> {
+    let aliases = options.aliases()?;
+    let query = options.query(query, &aliases);
+    if query.terms.is_empty() {
+        Err(Error::Usage(USAGE.to_string()))?;
+    }
+
+    // Against the API only the matching records are copied out of each page,
+    // the rest are looked at while the body is streamed and dropped
+    let manufacturers = match options.snapshot()? {
+        Some(snapshot) => searched(snapshot),
+        None => {
+            let filter = query.clone();
+            options
+                .client()?
+                .all_manufacturers_filtered(options.concurrency, options.max_pages, move |m| {
+                    filter.score(m).is_some()
+                })
+                .await?
+        }
+    };
+
+    // Search relevant (needle, BMW) in the manufacturers parsed, best matches first
+    // The index narrows the records down before scoring
+    let index = Index::build(&manufacturers);
+    let found_manufacturers = options.matches(&aliases, &index, &query, &manufacturers);
+
+    // Makes and details for every match, looked up concurrently instead of one command each
+    if options.enrich && !found_manufacturers.is_empty() {
+        let found = found_manufacturers.into_iter().cloned().collect();
+        let enriched = enrich::enrich(&options.client()?, found, options.concurrency).await?;
+        return print_results(
+            options,
+            "Manufacturer",
+            "manufacturers",
+            &enriched,
+            |enriched| enriched.description(),
+        );
+    }
+
+    // Tell user if no manufacturers are found and print manufacturers found
+    print_results(
+        options,
+        "Manufacturer",
+        "manufacturers",
+        &found_manufacturers,
+        |man| man.description(),
+    )
+}
+
+// The manufacturer an ID or a query names, the best match for a query
+fn pick<'a>(
+    options: &Options,
+    aliases: &Aliases,
+    index: &Index,
+    manufacturers: &'a [Manufacturer],
+    input: &str,
+) -> Result<&'a Manufacturer, Error> {
+    let found = match input.trim().parse::<u32>() {
+        Ok(id) => manufacturers
+            .iter()
+            .find(|manufacturer| manufacturer.id == id),
+        Err(_) => {
+            let query = options.query(input, aliases);
+            options
+                .matches(aliases, index, &query, manufacturers)
+                .into_iter()
+                .next()
+        }
+    };
+    found.ok_or(Error::NoResults(format!(
+        "No manufacturer matches {}",
+        input
+    )))
+}
+
+// Two manufacturers side by side, with their makes and every country they are registered in
+async fn compare(options: &Options, a: &str, b: &str) -> Result<(), Error> {
+    let aliases = options.aliases()?;
+    let manufacturers = all_manufacturers(options).await?;
+    let index = Index::build(&manufacturers);
+    let left = pick(options, &aliases, &index, &manufacturers, a)?;
+    let right = pick(options, &aliases, &index, &manufacturers, b)?;
+
+    let enriched = enrich::enrich(
+        &options.client()?,
+        vec![left.clone(), right.clone()],
+        options.concurrency,
+    )
+    .await?;
+    let comparison = Comparison::new(
+        &enriched[0],
+        &compare::registered_countries(&aliases, &manufacturers, left),
+        &enriched[1],
+        &compare::registered_countries(&aliases, &manufacturers, right),
+    );
+
+    match options.output {
+        Format::Text => println!("{}", comparison.render()),
+        format => {
+            let columns = output::select_columns::<compare::Row>(options.columns.as_deref())
+                .map_err(Error::Usage)?;
+            print!("{}", output::render(format, &comparison.rows, &columns));
+        }
+    }
+    Ok(())
+}
+
+// Contents of a file, or of stdin for "-"
+fn read_input(path: &str) -> Result<String, Error> {
+    let content = match path {
+        "-" => {
+            let mut content = String::new();
+            io::stdin()
+                .read_to_string(&mut content)
+                .map_err(|source| Error::Io {
+                    context: "Could not read standard input".to_string(),
+                    source,
+                })?;
+            content
+        }
+        _ => fs::read_to_string(path).map_err(|source| Error::Io {
+            context: format!("Could not read {}", path),
+            source,
+        })?,
+    };
+    Ok(content)
+}
+
+// One search per line of the input against a single download
+// Blank lines and lines starting with # are skipped
+async fn batch_search(options: &Options, path: &str) -> Result<(), Error> {
+    let content = read_input(path)?;
+    let queries: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    if queries.is_empty() {
+        Err(Error::Usage(format!("No search queries in {}", path)))?;
+    }
+
+    let aliases = options.aliases()?;
+    let manufacturers = all_manufacturers(options).await?;
+    let index = Index::build(&manufacturers);
+    let mut hits = Vec::new();
+    let mut without_hits = Vec::new();
+    for query in &queries {
+        let parsed = options.query(query, &aliases);
+        let found = options.matches(&aliases, &index, &parsed, &manufacturers);
+        if found.is_empty() {
+            without_hits.push(*query);
+        }
+        if options.output == Format::Text {
+            println!("== {}: {} manufacturers ==", query, found.len());
+            for (number, manufacturer) in found.iter().enumerate() {
+                println!("Manufacturer #{}", number + 1);
+                println!("{}", manufacturer.description());
+            }
+        }
+        hits.extend(found.into_iter().map(|manufacturer| QueryHit {
+            query,
+            manufacturer,
+        }));
+    }
+
+    if options.output != Format::Text {
+        let columns =
+            output::select_columns::<QueryHit>(options.columns.as_deref()).map_err(Error::Usage)?;
+        print!("{}", output::render(options.output, &hits, &columns));
+    }
+
+    // With structured output the summary goes to stderr so it does not break the format
+    let mut summary = format!(
+        "{} of {} queries found nothing",
+        without_hits.len(),
+        queries.len()
+    );
+    if !without_hits.is_empty() {
+        summary.push_str(&format!(": {}", without_hits.join(", ")));
+    }
+    match options.output {
+        Format::Text => println!("{}", summary),
+        _ => eprintln!("{}", summary),
+    }
+    if hits.is_empty() {
+        Err(Error::NoResults("No manufacturers found".to_string()))?;
+    }
+    Ok(())
+}
+
+// Count manufacturers per country or vehicle type, optionally only those matching a query
+// Every legal name counts, so aliases widen the query but nothing is folded
+async fn report(options: &Options, query: &str) -> Result<(), Error> {
+    let manufacturers = all_manufacturers(options).await?;
+    let query = options.query(query, &options.aliases()?);
+    let matching: Vec<Manufacturer> = match query.terms.is_empty() {
+        true => manufacturers,
+        false => Index::build(&manufacturers)
+            .rank(&query, &manufacturers)
+            .into_iter()
+            .map(|(manufacturer, _)| manufacturer.clone())
+            .collect(),
+    };
+    if matching.is_empty() {
+        Err(Error::NoResults("No manufacturers found".to_string()))?;
+    }
+
+    let groups = report::group(&matching, options.group_by);
+    let group_count = groups.len();
+    let groups = match options.top {
+        Some(top) => report::top(groups, top),
+        None => groups,
+    };
+
+    if options.output != Format::Text {
+        let columns = output::select_columns::<report::Group>(options.columns.as_deref())
+            .map_err(Error::Usage)?;
+        print!("{}", output::render(options.output, &groups, &columns));
         return Ok(());
     }
-    // Store your keyword into a variable
-    let keyword = &args[1];
 
-    // Create a client with reqwest, GET request Client
-    // Read response and parse it as JSON
-    let client = reqwest::Client::new();
-    let res = client
-        .get(API_URL)
-        .send()
-        .await?
-        .json::<serde_json::Value>()
+    let heading = match options.group_by {
+        GroupBy::Country => "country",
+        GroupBy::VehicleType => "vehicle type",
+    };
+    println!(
+        "{} manufacturers in {} groups by {}:",
+        matching.len(),
+        group_count,
+        heading
+    );
+    let width = groups
+        .iter()
+        .map(|group| group.key.chars().count())
+        .max()
+        .unwrap_or_default();
+    for group in &groups {
+        println!(
+            "\t{:<width$}  {:>6}  {:>5.1}%",
+            group.key,
+            group.count,
+            group.percent,
+            width = width
+        );
+    }
+    if options.group_by == GroupBy::VehicleType {
+        println!("Manufacturers building several vehicle types are counted in each of them");
+    }
+    Ok(())
+}
+
+// Print a numbered list the way the search always has, or the --output format
+// Structured output is printed even when empty so pipes get valid JSON or CSV
+fn print_results<T: Record>(
+    options: &Options,
+    label: &str,
+    plural: &str,
+    items: &[T],
+    description: impl Fn(&T) -> String,
+) -> Result<(), Error> {
+    if options.output != Format::Text {
+        print_structured(options, items)?;
+    }
+    if items.is_empty() {
+        Err(Error::NoResults(format!("No {} found", plural)))?;
+    }
+    if options.output == Format::Text {
+        println!("Found {} {}: ", items.len(), plural);
+        for (index, item) in items.iter().enumerate() {
+            println!("{} #{}", label, index + 1);
+            println!("{}", description(item));
+        }
+    }
+    Ok(())
+}
+
+// The items in the --output format with the --columns picked
+fn print_structured<T: Record>(options: &Options, items: &[T]) -> Result<(), Error> {
+    let columns = output::select_columns::<T>(options.columns.as_deref()).map_err(Error::Usage)?;
+    print!("{}", output::render(options.output, items, &columns));
+    Ok(())
+}
+
+// The --manufacturer, --make or --model value a browsing command needs
+fn required<'a>(value: &'a Option<String>, flag: &str) -> Result<&'a str, Error> {
+    value
+        .as_deref()
+        .ok_or(Error::Usage(format!("{} is required", flag)))
+}
+
+// Makes registered by a manufacturer
+async fn makes(options: &Options) -> Result<(), Error> {
+    let manufacturer = required(&options.manufacturer, "--manufacturer")?;
+    let makes = options
+        .client()?
+        .makes_for_manufacturer(manufacturer)
         .await?;
+    print_results(options, "Make", "makes", &makes, |make| make.description())
+}
 
-    // Grab the manufacturer JSON object
-    let manufacturer_json = res
-        .as_object()
-        .unwrap() // unsafely
-        .iter()
-        .find(|(key, _)| key == &"Results") // "Results in the JSON file
-        .unwrap() // unsafely
-        .1
-        .as_array()
-        .unwrap() // unsafely
-        .iter();
-
-    // Parse all the manufacturers
-    let manufacturers = manufacturer_json.map(|manufacturer_json| {
-        let obj = manufacturer_json.as_object().unwrap();
-        let country = obj.get("Country").unwrap().as_str();
-        let common_name = obj.get("Mfr_CommonName").unwrap().as_str();
-        let name = obj.get("Mfr_Name").unwrap().as_str();
-
-        Manufacturer {
-            name,
-            common_name,
-            country,
-        }
-    });
-
-    // Search relevant (needle, BMW) in the manufacturers parsed
-    let found_manufacturers = manufacturers
-        .filter(|manufacturer| manufacturer.contains(keyword))
-        .collect::<Vec<_>>(); // _ replaces Manufacturer
+// Models of a make, optionally for one model year
+async fn models(options: &Options) -> Result<(), Error> {
+    let make = required(&options.make, "--make")?;
+    let models = options
+        .client()?
+        .models_for_make(make, options.year)
+        .await?;
+    print_results(options, "Model", "models", &models, |model| {
+        model.description()
+    })
+}
 
-    // Tell user if no manufacturers are found and print manufacturers found
-    if found_manufacturers.is_empty() {
-        Err("No manufacturers found".into())
-    } else {
-        println!("Found {} manufacurers: ", found_manufacturers.len());
-        for (index, man) in found_manufacturers.iter().enumerate() {
-            println!("Manufacturer #{}", index + 1);
-            println!("{}", man.description());
+// Vehicle types built under a make
+async fn vehicle_types(options: &Options) -> Result<(), Error> {
+    let make = required(&options.make, "--make")?;
+    let vehicle_types = options.client()?.vehicle_types_for_make(make).await?;
+    print_results(
+        options,
+        "Vehicle Type",
+        "vehicle types",
+        &vehicle_types,
+        |vehicle_type| vehicle_type.description(),
+    )
+}
+
+// The vehicle recalls and complaints need, all three flags are required by NHTSA
+fn vehicle(options: &Options) -> Result<Vehicle, Error> {
+    Ok(Vehicle {
+        make: required(&options.make, "--make")?.to_string(),
+        model: required(&options.model, "--model")?.to_string(),
+        year: options
+            .year
+            .ok_or(Error::Usage("--year is required".to_string()))?,
+    })
+}
+
+// Safety recalls of a vehicle, newest campaigns first
+async fn recalls(options: &Options, source: &impl VehicleDataSource) -> Result<(), Error> {
+    let vehicle = vehicle(options)?;
+    let mut recalls = source.recalls(&vehicle).await?;
+    recalls.sort_by(|a, b| b.sort_key().cmp(&a.sort_key()));
+    print_results(options, "Recall", "recalls", &recalls, |recall| {
+        recall.description()
+    })
+}
+
+// Owner complaints about a vehicle, most recent first
+async fn complaints(options: &Options, source: &impl VehicleDataSource) -> Result<(), Error> {
+    let vehicle = vehicle(options)?;
+    let mut complaints = source.complaints(&vehicle).await?;
+    complaints.sort_by_key(|complaint| std::cmp::Reverse(complaint.odi_number));
+    print_results(
+        options,
+        "Complaint",
+        "complaints",
+        &complaints,
+        |complaint| complaint.description(),
+    )
+}
+
+// Validate the VIN locally, then ask vPIC for the rest
+async fn decode_vin(options: &Options, input: &str) -> Result<(), Error> {
+    let vin = Vin::parse(input)
+        .map_err(|e| Error::InvalidInput(format!("Invalid VIN {}: {}", input.trim(), e)))?;
+    let records = wmi_records(
+        &options.aliases()?,
+        &snapshot_manufacturers(options)?,
+        vin.wmi(),
+    );
+    // What the VIN itself tells is shown even when vPIC cannot be reached
+    if options.output == Format::Text {
+        println!("VIN: {}", vin);
+        println!("\tWMI: {} ({})", vin.wmi(), wmi_origin(vin.wmi()));
+        if !records.is_empty() {
+            println!("\tManufacturer Records: {}", records.join(", "));
         }
-        Ok(())
+        println!("\tVDS: {}", vin.vds());
+        println!("\tVIS: {}", vin.vis());
+        println!("\tCheck Digit: {} (valid)", vin.check_digit());
+        if let Some(year) = vin.model_year() {
+            println!("\tModel Year: {} (code {})", year, vin.model_year_code());
+        }
+    }
+
+    let decoded = options
+        .client()?
+        .decode_vin(&vin)
+        .await?
+        .ok_or(Error::NoResults(
+            "vPIC returned no decoding for this VIN".to_string(),
+        ))?;
+    let note = decoded.has_errors().then(|| decoded.error_text.clone());
+    match options.output {
+        Format::Text => println!("{}", decoded.description()),
+        _ => print_structured(
+            options,
+            &[VinDecoding {
+                vin,
+                manufacturers: records,
+                decoded,
+            }],
+        )?,
+    }
+    if let Some(note) = note {
+        eprintln!("Note from vPIC: {}", note);
+    }
+    Ok(())
+}
+
+// Make and country for a WMI from the built-in table, or at least its region
+fn wmi_origin(wmi: &str) -> String {
+    match vin::lookup_wmi(wmi) {
+        Some(entry) => format!("{}, {}", entry.make, entry.country),
+        None => vin::region(wmi).unwrap_or("unknown").to_string(),
+    }
+}
+
+// Manufacturers of the snapshot, none without one
+// VIN checks never download the whole list just to name the records of a make
+fn snapshot_manufacturers(options: &Options) -> Result<Vec<Manufacturer>, Error> {
+    Ok(options
+        .snapshot()?
+        .map(|snapshot| snapshot.manufacturers)
+        .unwrap_or_default())
+}
+
+// "NAME (ID 968)" for every manufacturer record of the make the WMI belongs to
+fn wmi_records(aliases: &Aliases, manufacturers: &[Manufacturer], wmi: &str) -> Vec<String> {
+    let Some(entry) = vin::lookup_wmi(wmi) else {
+        return Vec::new();
+    };
+    entry
+        .find_in(aliases, manufacturers)
+        .into_iter()
+        .map(|manufacturer| {
+            format!(
+                "{} (ID {})",
+                manufacturer.name.as_deref().unwrap_or_default(),
+                manufacturer.id
+            )
+        })
+        .collect()
+}
+
+// Validate every VIN in a file without touching the network
+// The VIN is the first column of each line, so CSV or TSV exports work as is
+fn validate_vins(options: &Options, path: &str) -> Result<(), Error> {
+    let content = read_input(path)?;
+
+    let aliases = options.aliases()?;
+    let manufacturers = snapshot_manufacturers(options)?;
+
+    let mut checks = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let field = line
+            .split([',', ';', '\t'])
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .trim_matches('"');
+        // Skip blank lines and a header row
+        if field.is_empty() || field.eq_ignore_ascii_case("vin") {
+            continue;
+        }
+
+        let result = match options.skip_check_digit {
+            true => Vin::parse_structure(field),
+            false => Vin::parse(field),
+        };
+        let manufacturers = match &result {
+            Ok(vin) => wmi_records(&aliases, &manufacturers, vin.wmi()),
+            Err(_) => Vec::new(),
+        };
+        checks.push(VinCheck {
+            line: index + 1,
+            input: field.to_string(),
+            result,
+            manufacturers,
+        });
+    }
+
+    // Text is one tab separated line per VIN under a header
+    match options.output {
+        Format::Text => {
+            println!("{}", VinCheck::COLUMNS.join("\t"));
+            for check in &checks {
+                let cells: Vec<String> = VinCheck::COLUMNS
+                    .iter()
+                    .map(|column| output::cell(&check.value(column)))
+                    .collect();
+                println!("{}", cells.join("\t"));
+            }
+        }
+        _ => print_structured(options, &checks)?,
+    }
+    let invalid = checks.iter().filter(|check| check.result.is_err()).count();
+    let valid = checks.len() - invalid;
+    eprintln!("{} valid, {} invalid", valid, invalid);
+    if invalid > 0 {
+        Err(Error::InvalidInput(format!("{} invalid VINs", invalid)))?;
+    }
+    Ok(())
+}
+
+// Whoever reads the output has seen enough once the pipe is closed
+fn write_stdout(args: fmt::Arguments) {
+    if let Err(e) = io::stdout().write_fmt(args) {
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            process::exit(0);
+        }
+        panic!("failed printing to stdout: {}", e);
+    }
+}
+
+// Turn main fn async with tokio
+// Errors are printed once and turned into their exit code
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+async fn run() -> Result<(), Error> {
+    // Read all arguments, print the usage if there is nothing to do
+    let (options, positional) = Options::parse(env::args().skip(1))?;
+    let positional: Vec<&str> = positional.iter().map(String::as_str).collect();
+
+    if let Some(input) = &options.input {
+        if !positional.is_empty() {
+            Err(Error::Usage(
+                "Give either a search query or --input, not both".to_string(),
+            ))?;
+        }
+        return batch_search(&options, input).await;
+    }
+
+    match positional.as_slice() {
+        [] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        ["decode-vin", vin] => decode_vin(&options, vin).await,
+        ["validate-vins", path] => validate_vins(&options, path),
+        ["makes"] => makes(&options).await,
+        ["models"] => models(&options).await,
+        ["vehicle-types"] => vehicle_types(&options).await,
+        // Saved responses stand in for NHTSA when --data-dir is given
+        ["recalls"] => match &options.data_dir {
+            Some(dir) => recalls(&options, &FixtureSource::new(dir)).await,
+            None => recalls(&options, &NhtsaSource::new(options.safety_client()?)).await,
+        },
+        ["complaints"] => match &options.data_dir {
+            Some(dir) => complaints(&options, &FixtureSource::new(dir)).await,
+            None => complaints(&options, &NhtsaSource::new(options.safety_client()?)).await,
+        },
+        ["sync"] => sync(&options).await,
+        ["diff", old, new] => diff(&options, old, new),
+        ["repl"] => repl::run(&options).await,
+        ["compare", a, b] => compare(&options, a, b).await,
+        ["report", words @ ..] => report(&options, &words.join(" ")).await,
+        [command, ..] if COMMANDS.contains(command) => Err(Error::Usage(USAGE.to_string())),
+        // Store your query into a variable, unquoted words are joined back together
+        words => search(&options, &words.join(" ")).await,
     }
 }
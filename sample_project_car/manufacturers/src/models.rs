@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+
+// Envelope every vPIC endpoint wraps its results in
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Response<T> {
+    pub count: u32,
+    pub message: String,
+    pub search_criteria: Option<String>,
+    pub results: Vec<T>,
+}
+
+// A manufacturer record from getallmanufacturers
+// Serialized with the vPIC field names so snapshots read back the same way
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Manufacturer {
+    #[serde(rename = "Mfr_ID")]
+    pub id: u32,
+    #[serde(rename = "Mfr_Name")]
+    pub name: Option<String>,
+    #[serde(rename = "Mfr_CommonName")]
+    pub common_name: Option<String>,
+    #[serde(rename = "Country")]
+    pub country: Option<String>,
+    #[serde(rename = "VehicleTypes", default)]
+    pub vehicle_types: Vec<VehicleType>,
+}
+
+// Kind of vehicle a manufacturer builds, e.g. "Passenger Car"
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct VehicleType {
+    pub is_primary: bool,
+    pub name: Option<String>,
+}
+
+// Full record from GetManufacturerDetails
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManufacturerDetails {
+    #[serde(rename = "Mfr_ID")]
+    pub id: u32,
+    #[serde(rename = "Mfr_Name")]
+    pub name: Option<String>,
+    #[serde(rename = "Mfr_CommonName")]
+    pub common_name: Option<String>,
+    #[serde(rename = "Country")]
+    pub country: Option<String>,
+    #[serde(rename = "Address")]
+    pub address: Option<String>,
+    #[serde(rename = "City")]
+    pub city: Option<String>,
+    #[serde(rename = "StateProvince")]
+    pub state_province: Option<String>,
+    #[serde(rename = "PostalCode")]
+    pub postal_code: Option<String>,
+    #[serde(rename = "ContactEmail")]
+    pub contact_email: Option<String>,
+    #[serde(rename = "ContactPhone")]
+    pub contact_phone: Option<String>,
+    #[serde(rename = "VehicleTypes", default)]
+    pub vehicle_types: Vec<VehicleType>,
+    #[serde(rename = "ManufacturerTypes", default)]
+    pub manufacturer_types: Vec<ManufacturerType>,
+}
+
+// E.g. "Completed Vehicle Manufacturer"
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ManufacturerType {
+    pub name: Option<String>,
+}
+
+// A make registered by a manufacturer, from GetMakeForManufacturer
+#[derive(Debug, Clone, Deserialize)]
+pub struct Make {
+    #[serde(rename = "Make_ID")]
+    pub id: u32,
+    #[serde(rename = "Make_Name")]
+    pub name: Option<String>,
+    #[serde(rename = "Mfr_Name")]
+    pub manufacturer_name: Option<String>,
+}
+
+// A model of a make, from GetModelsForMake and GetModelsForMakeYear
+#[derive(Debug, Clone, Deserialize)]
+pub struct Model {
+    #[serde(rename = "Model_ID")]
+    pub id: u32,
+    #[serde(rename = "Model_Name")]
+    pub name: Option<String>,
+    #[serde(rename = "Make_ID")]
+    pub make_id: u32,
+    #[serde(rename = "Make_Name")]
+    pub make_name: Option<String>,
+}
+
+// A vehicle type built under a make, from GetVehicleTypesForMake
+#[derive(Debug, Clone, Deserialize)]
+pub struct MakeVehicleType {
+    #[serde(rename = "VehicleTypeId")]
+    pub id: u32,
+    #[serde(rename = "VehicleTypeName")]
+    pub name: Option<String>,
+    #[serde(rename = "MakeId")]
+    pub make_id: u32,
+    #[serde(rename = "MakeName")]
+    pub make_name: Option<String>,
+}
+
+// Flat record from DecodeVinValues, vPIC sends every value as a string
+// and uses "" for unknown values
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct DecodedVin {
+    #[serde(rename = "VIN")]
+    pub vin: String,
+    pub make: String,
+    pub model: String,
+    pub model_year: String,
+    pub manufacturer: String,
+    pub vehicle_type: String,
+    pub body_class: String,
+    pub trim: String,
+    pub fuel_type_primary: String,
+    #[serde(rename = "DisplacementL")]
+    pub displacement_l: String,
+    pub engine_cylinders: String,
+    pub plant_city: String,
+    pub plant_country: String,
+    pub error_code: String,
+    pub error_text: String,
+}
+
+impl DecodedVin {
+    // vPIC reports "0" when the VIN decoded cleanly
+    pub fn has_errors(&self) -> bool {
+        self.error_code
+            .split(',')
+            .any(|code| !code.trim().is_empty() && code.trim() != "0")
+    }
+
+    // Only the fields vPIC could decode
+    pub fn description(&self) -> String {
+        let fields = [
+            ("Make", &self.make),
+            ("Model", &self.model),
+            ("Model Year", &self.model_year),
+            ("Trim", &self.trim),
+            ("Manufacturer", &self.manufacturer),
+            ("Vehicle Type", &self.vehicle_type),
+            ("Body Class", &self.body_class),
+            ("Fuel Type", &self.fuel_type_primary),
+            ("Displacement (L)", &self.displacement_l),
+            ("Cylinders", &self.engine_cylinders),
+            ("Plant City", &self.plant_city),
+            ("Plant Country", &self.plant_country),
+        ];
+        fields
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(label, value)| format!("\t{}: {}", label, value))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Implement a description for Manufacturer
+impl Manufacturer {
+    pub fn description(&self) -> String {
+        let name = self.name.as_deref().unwrap_or_default();
+        let common_name = self.common_name.as_deref().unwrap_or_default();
+        let country = self.country.as_deref().unwrap_or_default();
+        let vehicle_types = self
+            .vehicle_types
+            .iter()
+            .filter_map(|vehicle_type| {
+                let name = vehicle_type.name.as_deref()?;
+                Some(match vehicle_type.is_primary {
+                    true => format!("{} (primary)", name),
+                    false => name.to_string(),
+                })
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "\tID: {}\n\tName: {}\n\tCommon Name: {}\n\tCountry: {}\n\tVehicle Types: {}",
+            self.id, name, common_name, country, vehicle_types,
+        )
+    }
+}
+
+impl ManufacturerDetails {
+    // Address, contact and manufacturer types, only the fields vPIC has
+    pub fn description(&self) -> String {
+        let manufacturer_types = self
+            .manufacturer_types
+            .iter()
+            .filter_map(|manufacturer_type| manufacturer_type.name.clone())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let fields = [
+            ("Address", self.address.clone()),
+            ("City", self.city.clone()),
+            ("State/Province", self.state_province.clone()),
+            ("Postal Code", self.postal_code.clone()),
+            ("Email", self.contact_email.clone()),
+            ("Phone", self.contact_phone.clone()),
+            ("Manufacturer Types", Some(manufacturer_types)),
+        ];
+        fields
+            .iter()
+            .filter_map(|(label, value)| Some((label, value.as_deref()?.trim())))
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(label, value)| format!("\t{}: {}", label, value))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Make {
+    pub fn description(&self) -> String {
+        format!(
+            "\tID: {}\n\tName: {}\n\tManufacturer: {}",
+            self.id,
+            self.name.as_deref().unwrap_or_default(),
+            self.manufacturer_name.as_deref().unwrap_or_default(),
+        )
+    }
+}
+
+impl Model {
+    pub fn description(&self) -> String {
+        format!(
+            "\tID: {}\n\tName: {}\n\tMake: {}",
+            self.id,
+            self.name.as_deref().unwrap_or_default(),
+            self.make_name.as_deref().unwrap_or_default(),
+        )
+    }
+}
+
+impl MakeVehicleType {
+    pub fn description(&self) -> String {
+        format!(
+            "\tID: {}\n\tName: {}\n\tMake: {}",
+            self.id,
+            self.name.as_deref().unwrap_or_default(),
+            self.make_name.as_deref().unwrap_or_default(),
+        )
+    }
+}
@@ -0,0 +1,568 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+
+use crate::compare::Row;
+use crate::enrich::Enriched;
+use crate::models::{DecodedVin, Make, MakeVehicleType, Manufacturer, Model};
+use crate::report::Group;
+use crate::safety::{Complaint, Recall};
+use crate::vin::{self, Vin, VinError};
+
+// How results are printed, text is the original tab-indented description
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+    Table,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "csv" => Ok(Format::Csv),
+            "table" => Ok(Format::Table),
+            _ => Err(format!(
+                "Unknown output format {}, use text, json, ndjson, csv or table",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::Csv => "csv",
+            Format::Table => "table",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A result that can be printed as structured output
+// Column names are part of the output contract, keep them stable
+pub trait Record {
+    const COLUMNS: &'static [&'static str];
+
+    fn value(&self, column: &str) -> Value;
+}
+
+impl<T: Record> Record for &T {
+    const COLUMNS: &'static [&'static str] = T::COLUMNS;
+
+    fn value(&self, column: &str) -> Value {
+        (*self).value(column)
+    }
+}
+
+impl Record for Manufacturer {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "common_name",
+        "country",
+        "vehicle_types",
+        "primary_vehicle_type",
+    ];
+
+    fn value(&self, column: &str) -> Value {
+        match column {
+            "id" => json!(self.id),
+            "name" => json!(self.name),
+            "common_name" => json!(self.common_name),
+            "country" => json!(self.country),
+            "vehicle_types" => json!(self
+                .vehicle_types
+                .iter()
+                .filter_map(|vehicle_type| vehicle_type.name.as_deref())
+                .collect::<Vec<_>>()),
+            "primary_vehicle_type" => json!(self
+                .vehicle_types
+                .iter()
+                .find(|vehicle_type| vehicle_type.is_primary)
+                .and_then(|vehicle_type| vehicle_type.name.as_deref())),
+            _ => Value::Null,
+        }
+    }
+}
+
+// A manufacturer found by one query of a batch search
+pub struct QueryHit<'a> {
+    pub query: &'a str,
+    pub manufacturer: &'a Manufacturer,
+}
+
+impl Record for QueryHit<'_> {
+    const COLUMNS: &'static [&'static str] = &[
+        "query",
+        "id",
+        "name",
+        "common_name",
+        "country",
+        "vehicle_types",
+        "primary_vehicle_type",
+    ];
+
+    fn value(&self, column: &str) -> Value {
+        match column {
+            "query" => json!(self.query),
+            _ => self.manufacturer.value(column),
+        }
+    }
+}
+
+impl Record for Enriched {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "common_name",
+        "country",
+        "vehicle_types",
+        "primary_vehicle_type",
+        "makes",
+        "address",
+        "city",
+        "state_province",
+        "postal_code",
+        "contact_email",
+        "contact_phone",
+    ];
+
+    fn value(&self, column: &str) -> Value {
+        let details = self.details.as_ref();
+        match column {
+            "makes" => json!(self
+                .makes
+                .iter()
+                .filter_map(|make| make.name.as_deref())
+                .collect::<Vec<_>>()),
+            "address" => json!(details.and_then(|details| details.address.as_deref())),
+            "city" => json!(details.and_then(|details| details.city.as_deref())),
+            "state_province" => {
+                json!(details.and_then(|details| details.state_province.as_deref()))
+            }
+            "postal_code" => json!(details.and_then(|details| details.postal_code.as_deref())),
+            "contact_email" => json!(details.and_then(|details| details.contact_email.as_deref())),
+            "contact_phone" => json!(details.and_then(|details| details.contact_phone.as_deref())),
+            _ => self.manufacturer.value(column),
+        }
+    }
+}
+
+impl Record for Row {
+    const COLUMNS: &'static [&'static str] = &["field", "left", "right", "same"];
+
+    fn value(&self, column: &str) -> Value {
+        match column {
+            "field" => json!(self.field),
+            "left" => json!(self.left),
+            "right" => json!(self.right),
+            "same" => json!(self.same()),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Record for Make {
+    const COLUMNS: &'static [&'static str] = &["id", "name", "manufacturer_name"];
+
+    fn value(&self, column: &str) -> Value {
+        match column {
+            "id" => json!(self.id),
+            "name" => json!(self.name),
+            "manufacturer_name" => json!(self.manufacturer_name),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Record for Model {
+    const COLUMNS: &'static [&'static str] = &["id", "name", "make_id", "make_name"];
+
+    fn value(&self, column: &str) -> Value {
+        match column {
+            "id" => json!(self.id),
+            "name" => json!(self.name),
+            "make_id" => json!(self.make_id),
+            "make_name" => json!(self.make_name),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Record for MakeVehicleType {
+    const COLUMNS: &'static [&'static str] = &["id", "name", "make_id", "make_name"];
+
+    fn value(&self, column: &str) -> Value {
+        match column {
+            "id" => json!(self.id),
+            "name" => json!(self.name),
+            "make_id" => json!(self.make_id),
+            "make_name" => json!(self.make_name),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Record for Recall {
+    const COLUMNS: &'static [&'static str] = &[
+        "campaign",
+        "report_date",
+        "component",
+        "summary",
+        "consequence",
+        "remedy",
+        "park_it",
+    ];
+
+    fn value(&self, column: &str) -> Value {
+        match column {
+            "campaign" => json!(self.campaign),
+            "report_date" => json!(self.report_date),
+            "component" => json!(self.component),
+            "summary" => json!(self.summary),
+            "consequence" => json!(self.consequence),
+            "remedy" => json!(self.remedy),
+            "park_it" => json!(self.park_it),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Record for Complaint {
+    const COLUMNS: &'static [&'static str] = &[
+        "odi_number",
+        "date_filed",
+        "components",
+        "summary",
+        "crash",
+        "fire",
+        "injuries",
+        "deaths",
+    ];
+
+    fn value(&self, column: &str) -> Value {
+        match column {
+            "odi_number" => json!(self.odi_number),
+            "date_filed" => json!(self.date_complaint_filed),
+            "components" => json!(self.components),
+            "summary" => json!(self.summary),
+            "crash" => json!(self.crash),
+            "fire" => json!(self.fire),
+            "injuries" => json!(self.number_of_injuries),
+            "deaths" => json!(self.number_of_deaths),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Record for Group {
+    const COLUMNS: &'static [&'static str] = &["group", "count", "percent"];
+
+    fn value(&self, column: &str) -> Value {
+        match column {
+            "group" => json!(self.key),
+            "count" => json!(self.count),
+            // Two decimals are plenty for a share
+            "percent" => json!((self.percent * 100.0).round() / 100.0),
+            _ => Value::Null,
+        }
+    }
+}
+
+// One line of validate-vins, as text these columns are printed separated by tabs
+pub struct VinCheck {
+    pub line: usize,
+    // The VIN as it was read, shown when it is invalid
+    pub input: String,
+    pub result: Result<Vin, VinError>,
+    // The snapshot's records of the make, see WmiEntry::find_in
+    pub manufacturers: Vec<String>,
+}
+
+impl Record for VinCheck {
+    const COLUMNS: &'static [&'static str] = &[
+        "line",
+        "vin",
+        "status",
+        "wmi",
+        "vds",
+        "vis",
+        "model_year",
+        "make",
+        "country",
+        "error",
+        "manufacturers",
+    ];
+
+    fn value(&self, column: &str) -> Value {
+        let vin = match (&self.result, column) {
+            (_, "line") => return json!(self.line),
+            (_, "manufacturers") => return json!(self.manufacturers),
+            (Ok(_), "status") => return json!("valid"),
+            (Err(_), "status") => return json!("invalid"),
+            (Ok(vin), "vin") => return json!(vin.as_str()),
+            (Err(_), "vin") => return json!(self.input),
+            (Err(e), "error") => return json!(e.to_string()),
+            (Ok(vin), _) => vin,
+            (Err(_), _) => return Value::Null,
+        };
+        let entry = vin::lookup_wmi(vin.wmi());
+        match column {
+            "wmi" => json!(vin.wmi()),
+            "vds" => json!(vin.vds()),
+            "vis" => json!(vin.vis()),
+            "model_year" => json!(vin.model_year()),
+            "make" => json!(entry.map(|entry| entry.make)),
+            "country" => json!(entry.map(|entry| entry.country).or(vin::region(vin.wmi()))),
+            _ => Value::Null,
+        }
+    }
+}
+
+// A VIN decoded by vPIC next to what the VIN itself tells
+pub struct VinDecoding {
+    pub vin: Vin,
+    // The snapshot's records of the make, see WmiEntry::find_in
+    pub manufacturers: Vec<String>,
+    pub decoded: DecodedVin,
+}
+
+impl Record for VinDecoding {
+    const COLUMNS: &'static [&'static str] = &[
+        "vin",
+        "wmi",
+        "vds",
+        "vis",
+        "check_digit",
+        "model_year",
+        "country",
+        "manufacturers",
+        "make",
+        "model",
+        "trim",
+        "manufacturer",
+        "vehicle_type",
+        "body_class",
+        "fuel_type",
+        "displacement_l",
+        "cylinders",
+        "plant_city",
+        "plant_country",
+        "error_text",
+    ];
+
+    fn value(&self, column: &str) -> Value {
+        // vPIC sends "" for what it could not decode
+        let decoded = |value: &str| match value.is_empty() {
+            true => Value::Null,
+            false => json!(value),
+        };
+        let vin = &self.vin;
+        match column {
+            "vin" => json!(vin.as_str()),
+            "wmi" => json!(vin.wmi()),
+            "vds" => json!(vin.vds()),
+            "vis" => json!(vin.vis()),
+            "check_digit" => json!(vin.check_digit().to_string()),
+            "model_year" => json!(vin.model_year()),
+            "country" => json!(vin::lookup_wmi(vin.wmi())
+                .map(|entry| entry.country)
+                .or(vin::region(vin.wmi()))),
+            "manufacturers" => json!(self.manufacturers),
+            "make" => decoded(&self.decoded.make),
+            "model" => decoded(&self.decoded.model),
+            "trim" => decoded(&self.decoded.trim),
+            "manufacturer" => decoded(&self.decoded.manufacturer),
+            "vehicle_type" => decoded(&self.decoded.vehicle_type),
+            "body_class" => decoded(&self.decoded.body_class),
+            "fuel_type" => decoded(&self.decoded.fuel_type_primary),
+            "displacement_l" => decoded(&self.decoded.displacement_l),
+            "cylinders" => decoded(&self.decoded.engine_cylinders),
+            "plant_city" => decoded(&self.decoded.plant_city),
+            "plant_country" => decoded(&self.decoded.plant_country),
+            "error_text" => match self.decoded.has_errors() {
+                true => decoded(&self.decoded.error_text),
+                false => Value::Null,
+            },
+            _ => Value::Null,
+        }
+    }
+}
+
+// A manufacturer added, removed or changed between two snapshots
+pub struct DiffEntry<'a> {
+    // added, removed or changed
+    pub change: &'static str,
+    // The newer record of a changed manufacturer
+    pub manufacturer: &'a Manufacturer,
+    // Changed columns with the old and the new value
+    pub fields: Vec<(&'static str, Value, Value)>,
+}
+
+// Columns whose value differs between two records of one manufacturer
+pub fn changed_fields(
+    before: &Manufacturer,
+    after: &Manufacturer,
+) -> Vec<(&'static str, Value, Value)> {
+    Manufacturer::COLUMNS
+        .iter()
+        .map(|column| (*column, before.value(column), after.value(column)))
+        .filter(|(_, was, is)| was != is)
+        .collect()
+}
+
+impl Record for DiffEntry<'_> {
+    const COLUMNS: &'static [&'static str] = &["change", "id", "name", "country", "fields"];
+
+    fn value(&self, column: &str) -> Value {
+        match column {
+            "change" => json!(self.change),
+            "fields" => Value::Object(
+                self.fields
+                    .iter()
+                    .map(|(column, was, is)| (column.to_string(), json!({"from": was, "to": is})))
+                    .collect(),
+            ),
+            _ => self.manufacturer.value(column),
+        }
+    }
+}
+
+// Check a --columns selection, no selection means every column
+pub fn select_columns<T: Record>(selection: Option<&str>) -> Result<Vec<&'static str>, String> {
+    let Some(selection) = selection else {
+        return Ok(T::COLUMNS.to_vec());
+    };
+    selection
+        .split(',')
+        .map(str::trim)
+        .filter(|column| !column.is_empty())
+        .map(|column| {
+            T::COLUMNS
+                .iter()
+                .find(|known| **known == column)
+                .copied()
+                .ok_or(format!(
+                    "Unknown column {}, available: {}",
+                    column,
+                    T::COLUMNS.join(",")
+                ))
+        })
+        .collect()
+}
+
+// Plain text for CSV and table cells, lists are joined with "; "
+pub fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(cell).collect::<Vec<_>>().join("; "),
+        other => other.to_string(),
+    }
+}
+
+// Quote a CSV field when it contains a separator, quote or line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// One JSON object with the columns in the selected order
+fn json_object<T: Record>(item: &T, columns: &[&str]) -> String {
+    let fields: Vec<String> = columns
+        .iter()
+        .map(|column| format!("{}:{}", json!(column), item.value(column)))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+// Render the items in a structured format, Text is left to the caller's description
+pub fn render<T: Record>(format: Format, items: &[T], columns: &[&str]) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Text => {}
+        Format::Json => {
+            let objects: Vec<String> = items
+                .iter()
+                .map(|item| format!("  {}", json_object(item, columns)))
+                .collect();
+            match objects.is_empty() {
+                true => out.push_str("[]\n"),
+                false => out.push_str(&format!("[\n{}\n]\n", objects.join(",\n"))),
+            }
+        }
+        Format::Ndjson => {
+            for item in items {
+                out.push_str(&json_object(item, columns));
+                out.push('\n');
+            }
+        }
+        Format::Csv => {
+            out.push_str(&columns.join(","));
+            out.push('\n');
+            for item in items {
+                let row: Vec<String> = columns
+                    .iter()
+                    .map(|column| csv_field(&cell(&item.value(column))))
+                    .collect();
+                out.push_str(&row.join(","));
+                out.push('\n');
+            }
+        }
+        Format::Table => {
+            let rows: Vec<Vec<String>> = items
+                .iter()
+                .map(|item| {
+                    columns
+                        .iter()
+                        .map(|column| cell(&item.value(column)))
+                        .collect()
+                })
+                .collect();
+            let widths: Vec<usize> = columns
+                .iter()
+                .enumerate()
+                .map(|(index, column)| {
+                    rows.iter()
+                        .map(|row| row[index].chars().count())
+                        .chain([column.len()])
+                        .max()
+                        .unwrap_or_default()
+                })
+                .collect();
+
+            let line = |cells: &[String]| {
+                let padded: Vec<String> = cells
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                    .collect();
+                format!("{}\n", padded.join("  ").trim_end())
+            };
+            let header: Vec<String> = columns.iter().map(|column| column.to_string()).collect();
+            let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+            out.push_str(&line(&header));
+            out.push_str(&line(&rule));
+            for row in &rows {
+                out.push_str(&line(row));
+            }
+        }
+    }
+    out
+}
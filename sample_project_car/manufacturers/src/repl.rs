@@ -0,0 +1,392 @@
+// Interactive search session, the dataset is fetched once and searched as often as needed
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use manufacturers::output::{self, Format, Record};
+use manufacturers::search::{fold, Index};
+use manufacturers::snapshot::Snapshot;
+use manufacturers::{Error, Manufacturer};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use crate::{all_manufacturers, Options};
+
+const HELP: &str = "Type a search query, e.g. bmw or country:germany name:motor
+  :show <ID>                 Full details of a manufacturer
+  :export <FILE> [FORMAT]    Save the last results, the format defaults to the extension
+  :help                      This help
+  :quit                      Leave, Ctrl-D works too
+Tab completes commands and country names after country:";
+
+const REPL_COMMANDS: &[&str] = &[":show", ":export", ":help", ":quit"];
+
+// Tab completion for commands and the countries found in the dataset
+struct ReplHelper {
+    countries: Vec<String>,
+}
+
+impl ReplHelper {
+    fn new(manufacturers: &[Manufacturer]) -> Self {
+        let mut countries: Vec<String> = manufacturers
+            .iter()
+            .filter_map(|manufacturer| manufacturer.country.clone())
+            .filter(|country| !country.trim().is_empty())
+            .collect();
+        countries.sort();
+        countries.dedup();
+        ReplHelper { countries }
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        if before.starts_with(':') && !before.contains(' ') {
+            let commands = REPL_COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(before))
+                .map(|command| Pair {
+                    display: command.to_string(),
+                    replacement: format!("{} ", command),
+                })
+                .collect();
+            return Ok((0, commands));
+        }
+
+        // Complete the value of the last country: term, quoting names with spaces
+        let Some(prefix_at) = before.rfind("country:") else {
+            return Ok((pos, Vec::new()));
+        };
+        let start = prefix_at + "country:".len();
+        let typed = &before[start..];
+        let partial = match typed.strip_prefix('"') {
+            Some(quoted) if !quoted.contains('"') => quoted,
+            None if !typed.contains(char::is_whitespace) => typed,
+            _ => return Ok((pos, Vec::new())),
+        };
+        let partial = fold(partial);
+        let countries = self
+            .countries
+            .iter()
+            .filter(|country| fold(country).starts_with(&partial))
+            .map(|country| Pair {
+                display: country.clone(),
+                replacement: match country.contains(' ') {
+                    true => format!("\"{}\"", country),
+                    false => country.clone(),
+                },
+            })
+            .collect();
+        Ok((start, countries))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+// Search history is kept next to the snapshot
+fn history_path() -> PathBuf {
+    Snapshot::default_path().with_file_name("history")
+}
+
+// Format for :export, from the name given or the file extension
+fn export_format(path: &Path, format: Option<&str>) -> Result<Format, Error> {
+    if let Some(format) = format {
+        return format.parse().map_err(Error::Usage);
+    }
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => Ok(Format::Json),
+        Some("ndjson") | Some("jsonl") => Ok(Format::Ndjson),
+        Some("csv") => Ok(Format::Csv),
+        Some("txt") => Ok(Format::Table),
+        _ => Err(Error::Usage(
+            "Give the format, e.g. :export results json".to_string(),
+        )),
+    }
+}
+
+// What one line of input asks for
+enum Action {
+    Search,
+    Show,
+    Export,
+    Help,
+    Quit,
+}
+
+pub async fn run(options: &Options) -> Result<(), Error> {
+    let aliases = options.aliases()?;
+    let manufacturers = all_manufacturers(options).await?;
+    let index = Index::build(&manufacturers);
+    println!(
+        "{} manufacturers loaded, :help lists the commands",
+        manufacturers.len()
+    );
+
+    let mut editor: Editor<ReplHelper, DefaultHistory> =
+        Editor::new().map_err(|e| Error::Internal(e.to_string()))?;
+    editor.set_helper(Some(ReplHelper::new(&manufacturers)));
+    let history = history_path();
+    // There is no history on the first run
+    let _ = editor.load_history(&history);
+
+    let mut last: Vec<&Manufacturer> = Vec::new();
+    loop {
+        let line = match editor.readline("manufacturers> ") {
+            Ok(line) => line,
+            // Ctrl-C clears the line, Ctrl-D leaves
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(Error::Internal(e.to_string())),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+
+        let mut words = line.split_whitespace();
+        let action = match line.starts_with(':') {
+            false => Action::Search,
+            true => match words.next().unwrap_or_default() {
+                ":show" => Action::Show,
+                ":export" => Action::Export,
+                ":help" => Action::Help,
+                ":quit" | ":q" | ":exit" => Action::Quit,
+                command => {
+                    println!("Unknown command {}, try :help", command);
+                    continue;
+                }
+            },
+        };
+
+        // Errors are reported and the session goes on
+        let result = match action {
+            Action::Search => {
+                let query = options.query(line, &aliases);
+                // A bare prefix like name: has nothing to search for
+                match query.terms.is_empty() {
+                    true => Err(Error::Usage(
+                        "Type a search term, e.g. bmw or name:motor".to_string(),
+                    )),
+                    false => {
+                        last = options.matches(&aliases, &index, &query, &manufacturers);
+                        print_summary(&last);
+                        Ok(())
+                    }
+                }
+            }
+            Action::Show => show(options, &manufacturers, words.next()).await,
+            Action::Export => export(&last, words.next(), words.next()),
+            Action::Help => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            Action::Quit => break,
+        };
+        if let Err(e) = result {
+            println!("Error: {}", e);
+        }
+    }
+
+    if let Some(dir) = history.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    // Losing the history is not worth failing the session for
+    if let Err(e) = editor.save_history(&history) {
+        eprintln!("Could not save the history: {}", e);
+    }
+    Ok(())
+}
+
+// One line per result, :show gives the rest
+fn print_summary(found: &[&Manufacturer]) {
+    println!("Found {} manufacturers", found.len());
+    for manufacturer in found {
+        let details: Vec<&str> = [&manufacturer.common_name, &manufacturer.country]
+            .into_iter()
+            .filter_map(|value| value.as_deref())
+            .filter(|value| !value.is_empty())
+            .collect();
+        println!(
+            "\t{:>6}  {} ({})",
+            manufacturer.id,
+            manufacturer.name.as_deref().unwrap_or_default(),
+            details.join(", ")
+        );
+    }
+}
+
+// Everything known about one manufacturer, contact details come from vPIC when reachable
+async fn show(
+    options: &Options,
+    manufacturers: &[Manufacturer],
+    id: Option<&str>,
+) -> Result<(), Error> {
+    let id: u32 = id
+        .and_then(|id| id.parse().ok())
+        .ok_or(Error::Usage("Usage: :show <ID>".to_string()))?;
+    let manufacturer = manufacturers
+        .iter()
+        .find(|manufacturer| manufacturer.id == id)
+        .ok_or(Error::NoResults(format!("No manufacturer with ID {}", id)))?;
+    println!("{}", manufacturer.description());
+
+    // By ID, a name can match several manufacturers or none when it has a slash
+    let details = options
+        .client()?
+        .manufacturer_details(&id.to_string())
+        .await;
+    match details {
+        Ok(details) => {
+            if let Some(details) = details.iter().find(|details| details.id == id) {
+                println!("{}", details.description());
+            }
+        }
+        Err(e) => println!("\t(no contact details: {})", e),
+    }
+    Ok(())
+}
+
+// Write the last result set in a structured format
+fn export(last: &[&Manufacturer], path: Option<&str>, format: Option<&str>) -> Result<(), Error> {
+    let path = Path::new(path.ok_or(Error::Usage("Usage: :export <FILE> [FORMAT]".to_string()))?);
+    if last.is_empty() {
+        Err(Error::NoResults(
+            "Nothing to export, search first".to_string(),
+        ))?;
+    }
+    let format = export_format(path, format)?;
+    if format == Format::Text {
+        Err(Error::Usage(
+            "Export as json, ndjson, csv or table".to_string(),
+        ))?;
+    }
+    let content = output::render(format, last, Manufacturer::COLUMNS);
+    fs::write(path, content).map_err(|source| Error::Io {
+        context: format!("Could not write {}", path.display()),
+        source,
+    })?;
+    println!("Saved {} manufacturers to {}", last.len(), path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustyline::history::MemHistory;
+
+    fn helper() -> ReplHelper {
+        let manufacturer = |country: Option<&str>| Manufacturer {
+            id: 1,
+            name: None,
+            common_name: None,
+            country: country.map(str::to_string),
+            vehicle_types: Vec::new(),
+        };
+        ReplHelper::new(&[
+            manufacturer(Some("GERMANY")),
+            manufacturer(Some("UNITED STATES (USA)")),
+            manufacturer(Some("UNITED KINGDOM (UK)")),
+            manufacturer(Some("GERMANY")),
+            manufacturer(Some(" ")),
+            manufacturer(None),
+        ])
+    }
+
+    fn complete(line: &str) -> (usize, Vec<(String, String)>) {
+        let history = MemHistory::new();
+        let (start, pairs) = helper()
+            .complete(line, line.len(), &Context::new(&history))
+            .unwrap();
+        let pairs = pairs
+            .into_iter()
+            .map(|pair| (pair.display, pair.replacement))
+            .collect();
+        (start, pairs)
+    }
+
+    fn pair(display: &str, replacement: &str) -> (String, String) {
+        (display.to_string(), replacement.to_string())
+    }
+
+    #[test]
+    fn countries_are_collected_once() {
+        assert_eq!(
+            helper().countries,
+            ["GERMANY", "UNITED KINGDOM (UK)", "UNITED STATES (USA)"]
+        );
+    }
+
+    #[test]
+    fn commands_complete_from_their_prefix() {
+        assert_eq!(complete(":s"), (0, vec![pair(":show", ":show ")]));
+        assert_eq!(complete(":").1.len(), REPL_COMMANDS.len());
+        assert!(complete(":x").1.is_empty());
+        // Arguments of a command are not completed
+        assert_eq!(complete(":show 9"), (7, Vec::new()));
+    }
+
+    #[test]
+    fn countries_complete_after_the_prefix() {
+        assert_eq!(
+            complete("bmw country:ger"),
+            (12, vec![pair("GERMANY", "GERMANY")])
+        );
+        // An unquoted value ends at a space
+        assert_eq!(complete("country:united k"), (16, Vec::new()));
+        // Case is ignored, names with spaces are quoted
+        assert_eq!(
+            complete("country:Unit"),
+            (
+                8,
+                vec![
+                    pair("UNITED KINGDOM (UK)", "\"UNITED KINGDOM (UK)\""),
+                    pair("UNITED STATES (USA)", "\"UNITED STATES (USA)\""),
+                ]
+            )
+        );
+        assert_eq!(
+            complete("country:\"united s"),
+            (
+                8,
+                vec![pair("UNITED STATES (USA)", "\"UNITED STATES (USA)\"")]
+            )
+        );
+        assert_eq!(complete("country:").1.len(), 3);
+    }
+
+    #[test]
+    fn nothing_completes_outside_a_country_term() {
+        assert_eq!(complete("germ"), (4, Vec::new()));
+        assert_eq!(complete("name:ger"), (8, Vec::new()));
+        // The quoted value is already closed
+        assert_eq!(complete("country:\"GERMANY\""), (17, Vec::new()));
+        // The cursor decides, not the end of the line
+        let history = MemHistory::new();
+        let (start, pairs) = helper()
+            .complete("country:ger name:x", 11, &Context::new(&history))
+            .unwrap();
+        assert_eq!(start, 8);
+        assert_eq!(pairs.len(), 1);
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::models::Manufacturer;
+
+// What manufacturers are counted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Country,
+    VehicleType,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "country" => Ok(GroupBy::Country),
+            "vehicle-type" => Ok(GroupBy::VehicleType),
+            _ => Err(format!("Unknown group {}, use country or vehicle-type", s)),
+        }
+    }
+}
+
+// Records without a value are counted under this key
+pub const UNKNOWN: &str = "(unknown)";
+
+// One line of a report
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub key: String,
+    pub count: usize,
+    // Share of all manufacturers, in percent
+    pub percent: f64,
+}
+
+fn keys(manufacturer: &Manufacturer, by: GroupBy) -> Vec<String> {
+    let keys: Vec<String> = match by {
+        GroupBy::Country => manufacturer.country.iter().cloned().collect(),
+        GroupBy::VehicleType => manufacturer
+            .vehicle_types
+            .iter()
+            .filter_map(|vehicle_type| vehicle_type.name.clone())
+            .collect(),
+    };
+    let mut keys: Vec<String> = keys
+        .into_iter()
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .collect();
+    keys.sort();
+    keys.dedup();
+    match keys.is_empty() {
+        true => vec![UNKNOWN.to_string()],
+        false => keys,
+    }
+}
+
+// Count manufacturers per group, largest first, ties by key
+// A manufacturer building several vehicle types counts in each of them,
+// so those percentages can add up to more than 100
+pub fn group(manufacturers: &[Manufacturer], by: GroupBy) -> Vec<Group> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for manufacturer in manufacturers {
+        for key in keys(manufacturer, by) {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+
+    let total = manufacturers.len().max(1) as f64;
+    let mut groups: Vec<Group> = counts
+        .into_iter()
+        .map(|(key, count)| Group {
+            key,
+            count,
+            percent: count as f64 * 100.0 / total,
+        })
+        .collect();
+    groups.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    groups
+}
+
+// Keep the first `top` groups and fold the rest into one "(N others)" line
+pub fn top(groups: Vec<Group>, top: usize) -> Vec<Group> {
+    if groups.len() <= top {
+        return groups;
+    }
+    let mut groups = groups;
+    let rest = groups.split_off(top);
+    groups.push(Group {
+        key: match rest.len() {
+            1 => "(1 other)".to_string(),
+            others => format!("({} others)", others),
+        },
+        count: rest.iter().map(|group| group.count).sum(),
+        percent: rest.iter().map(|group| group.percent).sum(),
+    });
+    groups
+}
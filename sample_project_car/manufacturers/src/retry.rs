@@ -0,0 +1,120 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::StatusCode;
+
+// How long to wait for a connection and for a whole response
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Retries with exponential backoff for rate limits, server errors and dropped connections
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    // Attempts after the first one, 0 disables retrying
+    pub max_retries: u32,
+    // Wait before the first retry, doubled on every following one
+    pub base_delay: Duration,
+    // Upper bound for a single wait, a longer Retry-After gives up instead
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    // Statuses worth asking again for, everything else is final
+    pub fn is_retryable(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    // Wait before retry number `attempt` (0 based), None when it is time to give up
+    // The server's Retry-After wins over the backoff when it is within max_delay
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        if let Some(retry_after) = retry_after {
+            return (retry_after <= self.max_delay).then_some(retry_after);
+        }
+
+        // Equal jitter: half the backoff is fixed, the other half random
+        // so clients that failed together do not retry together
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = backoff / 2;
+        Some(half + half.mul_f64(jitter()))
+    }
+}
+
+// A number in [0, 1), good enough to spread retries without a rand dependency
+fn jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or_default();
+    // xorshift to scatter the low bits of the clock
+    let mut x = u64::from(nanos) | 1;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    (x % 1_000_000) as f64 / 1_000_000.0
+}
+
+// Retry-After is either a number of seconds or an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = parse_http_date(value)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(at.saturating_sub(now)))
+}
+
+// Seconds since the epoch for an IMF-fixdate such as "Sun, 06 Nov 1994 08:49:37 GMT"
+fn parse_http_date(value: &str) -> Option<u64> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let day: u64 = day.parse().ok()?;
+    let month = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ]
+    .iter()
+    .position(|name| name == month)? as u64
+        + 1;
+    let year: u64 = year.parse().ok()?;
+    let mut clock = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (clock.next()??, clock.next()??, clock.next()??);
+    if year < 1970 || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    // Days from 1970-01-01 to the civil date, counting years from March
+    let (y, m) = match month <= 2 {
+        true => (year - 1, month + 9),
+        false => (year, month - 3),
+    };
+    let era = y / 400;
+    let year_of_era = y % 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146097 + day_of_era).checked_sub(719468)?;
+    Some(days * 86400 + hours * 3600 + minutes * 60 + seconds)
+}
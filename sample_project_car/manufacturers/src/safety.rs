@@ -0,0 +1,261 @@
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::client::VpicClient;
+use crate::error::Error;
+
+// Where NHTSA publishes recalls and complaints, a different host than vPIC
+pub const DEFAULT_SAFETY_URL: &str = "https://api.nhtsa.gov";
+
+// The vehicle recalls and complaints are looked up for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vehicle {
+    pub make: String,
+    pub model: String,
+    pub year: u16,
+}
+
+impl fmt::Display for Vehicle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.year, self.make, self.model)
+    }
+}
+
+// A safety recall campaign from recallsByVehicle
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Recall {
+    #[serde(rename = "NHTSACampaignNumber")]
+    pub campaign: String,
+    #[serde(rename = "ReportReceivedDate", default)]
+    pub report_date: Option<String>,
+    #[serde(rename = "Component", default)]
+    pub component: Option<String>,
+    #[serde(rename = "Summary", default)]
+    pub summary: Option<String>,
+    #[serde(rename = "Consequence", default)]
+    pub consequence: Option<String>,
+    #[serde(rename = "Remedy", default)]
+    pub remedy: Option<String>,
+    // Owners are told not to drive the vehicle until it is repaired
+    #[serde(rename = "parkIt", default)]
+    pub park_it: bool,
+}
+
+// An owner complaint from complaintsByVehicle
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Complaint {
+    pub odi_number: u64,
+    #[serde(default)]
+    pub date_complaint_filed: Option<String>,
+    #[serde(default)]
+    pub components: Option<String>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub crash: bool,
+    #[serde(default)]
+    pub fire: bool,
+    #[serde(default)]
+    pub number_of_injuries: u32,
+    #[serde(default)]
+    pub number_of_deaths: u32,
+}
+
+// Envelope of the NHTSA safety endpoints, the casing of "results" varies between them
+#[derive(Debug, Deserialize)]
+struct SafetyResponse<T> {
+    #[serde(alias = "Results")]
+    results: Vec<T>,
+}
+
+// Anything that can tell the recalls and complaints of a vehicle
+// The futures are Send so lookups can run on spawned tasks
+pub trait VehicleDataSource {
+    fn recalls(&self, vehicle: &Vehicle)
+        -> impl Future<Output = Result<Vec<Recall>, Error>> + Send;
+
+    fn complaints(
+        &self,
+        vehicle: &Vehicle,
+    ) -> impl Future<Output = Result<Vec<Complaint>, Error>> + Send;
+}
+
+// The NHTSA recall and complaint API, through a client so caching, retries
+// and timeouts work the same as for vPIC
+#[derive(Debug, Clone)]
+pub struct NhtsaSource {
+    client: VpicClient,
+}
+
+impl NhtsaSource {
+    // `client` has to point at DEFAULT_SAFETY_URL or a mirror of it
+    pub fn new(client: VpicClient) -> Self {
+        NhtsaSource { client }
+    }
+
+    async fn lookup<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        vehicle: &Vehicle,
+    ) -> Result<Vec<T>, Error> {
+        let query = [
+            ("make", vehicle.make.clone()),
+            ("model", vehicle.model.clone()),
+            ("modelYear", vehicle.year.to_string()),
+        ];
+        let response: SafetyResponse<T> = self.client.get_json(path, &query).await?;
+        Ok(response.results)
+    }
+}
+
+impl VehicleDataSource for NhtsaSource {
+    async fn recalls(&self, vehicle: &Vehicle) -> Result<Vec<Recall>, Error> {
+        self.lookup("recalls/recallsByVehicle", vehicle).await
+    }
+
+    async fn complaints(&self, vehicle: &Vehicle) -> Result<Vec<Complaint>, Error> {
+        self.lookup("complaints/complaintsByVehicle", vehicle).await
+    }
+}
+
+// Saved NHTSA responses in a directory, e.g. recalls-honda-accord-2003.json
+// Lets tests and demos run without the network
+#[derive(Debug, Clone)]
+pub struct FixtureSource {
+    dir: PathBuf,
+}
+
+impl FixtureSource {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FixtureSource { dir: dir.into() }
+    }
+
+    // File holding the `kind` responses for the vehicle, names are lowercase with dashes
+    pub fn path(&self, kind: &str, vehicle: &Vehicle) -> PathBuf {
+        let name = format!(
+            "{}-{}-{}-{}.json",
+            kind, vehicle.make, vehicle.model, vehicle.year
+        )
+        .to_lowercase()
+        .replace(' ', "-");
+        self.dir.join(name)
+    }
+
+    fn read<T: DeserializeOwned>(&self, kind: &str, vehicle: &Vehicle) -> Result<Vec<T>, Error> {
+        let path = self.path(kind, vehicle);
+        let body = fs::read_to_string(&path).map_err(|source| Error::Io {
+            context: format!("No {} recorded for {} in {}", kind, vehicle, path.display()),
+            source,
+        })?;
+        let response: SafetyResponse<T> =
+            serde_json::from_str(&body).map_err(|source| Error::Decode {
+                url: path_url(&path),
+                source,
+            })?;
+        Ok(response.results)
+    }
+}
+
+// Decode errors name a URL, for fixtures that is the file
+fn path_url(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+impl VehicleDataSource for FixtureSource {
+    async fn recalls(&self, vehicle: &Vehicle) -> Result<Vec<Recall>, Error> {
+        self.read("recalls", vehicle)
+    }
+
+    async fn complaints(&self, vehicle: &Vehicle) -> Result<Vec<Complaint>, Error> {
+        self.read("complaints", vehicle)
+    }
+}
+
+impl Recall {
+    // Orders recalls by when they were reported: the received date, else the year the
+    // campaign number starts with, then the number itself to break ties
+    pub fn sort_key(&self) -> ((u32, u32, u32), &str) {
+        let date = self
+            .received()
+            .or_else(|| Some((self.campaign_year()?, 0, 0)))
+            .unwrap_or_default();
+        (date, &self.campaign)
+    }
+
+    // (year, month, day) of ReportReceivedDate, which the API writes as dd/mm/yyyy
+    fn received(&self) -> Option<(u32, u32, u32)> {
+        let date = self.report_date.as_deref()?;
+        let mut parts = date.trim().splitn(3, '/').map(|part| part.parse().ok());
+        let (day, month, year) = (parts.next()??, parts.next()??, parts.next()??);
+        Some((year, month, day))
+    }
+
+    // Campaign numbers such as 99V123000 start with two digits of the year, NHTSA
+    // numbered its first campaigns in 1966 so 66 to 99 are the last century
+    fn campaign_year(&self) -> Option<u32> {
+        let year: u32 = self.campaign.get(..2)?.parse().ok()?;
+        Some(match year {
+            66.. => 1900 + year,
+            _ => 2000 + year,
+        })
+    }
+
+    pub fn description(&self) -> String {
+        let mut lines = vec![format!("\tCampaign: {}", self.campaign)];
+        let fields = [
+            ("Reported", &self.report_date),
+            ("Component", &self.component),
+            ("Summary", &self.summary),
+            ("Consequence", &self.consequence),
+            ("Remedy", &self.remedy),
+        ];
+        for (label, value) in fields {
+            if let Some(value) = value.as_deref().filter(|value| !value.is_empty()) {
+                lines.push(format!("\t{}: {}", label, value));
+            }
+        }
+        if self.park_it {
+            lines.push("\tDo not drive until repaired".to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+impl Complaint {
+    pub fn description(&self) -> String {
+        let mut lines = vec![format!("\tODI Number: {}", self.odi_number)];
+        let fields = [
+            ("Filed", &self.date_complaint_filed),
+            ("Components", &self.components),
+            ("Summary", &self.summary),
+        ];
+        for (label, value) in fields {
+            if let Some(value) = value.as_deref().filter(|value| !value.is_empty()) {
+                lines.push(format!("\t{}: {}", label, value));
+            }
+        }
+        let mut incidents = Vec::new();
+        if self.crash {
+            incidents.push("crash".to_string());
+        }
+        if self.fire {
+            incidents.push("fire".to_string());
+        }
+        if self.number_of_injuries > 0 {
+            incidents.push(format!("{} injured", self.number_of_injuries));
+        }
+        if self.number_of_deaths > 0 {
+            incidents.push(format!("{} dead", self.number_of_deaths));
+        }
+        if !incidents.is_empty() {
+            lines.push(format!("\tIncidents: {}", incidents.join(", ")));
+        }
+        lines.join("\n")
+    }
+}
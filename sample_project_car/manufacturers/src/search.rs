@@ -0,0 +1,382 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::str::FromStr;
+
+use crate::alias::{normalize, Aliases};
+use crate::models::Manufacturer;
+use crate::stream::ManufacturerRef;
+
+// Lowercase and strip accents from the Latin letters found in manufacturer names
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => folded.push('a'),
+            'æ' => folded.push_str("ae"),
+            'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => folded.push('c'),
+            'ď' | 'đ' => folded.push('d'),
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => folded.push('e'),
+            'ĝ' | 'ğ' | 'ġ' | 'ģ' => folded.push('g'),
+            'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => folded.push('i'),
+            'ł' | 'ľ' | 'ĺ' | 'ļ' => folded.push('l'),
+            'ñ' | 'ń' | 'ņ' | 'ň' => folded.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => folded.push('o'),
+            'œ' => folded.push_str("oe"),
+            'ŕ' | 'ŗ' | 'ř' => folded.push('r'),
+            'ś' | 'ŝ' | 'ş' | 'š' => folded.push('s'),
+            'ß' => folded.push_str("ss"),
+            'ţ' | 'ť' => folded.push('t'),
+            'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => folded.push('u'),
+            'ý' | 'ÿ' => folded.push('y'),
+            'ź' | 'ż' | 'ž' => folded.push('z'),
+            _ => folded.push(c),
+        }
+    }
+    folded
+}
+
+// Edit distance between two strings, counted in chars
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Manufacturer fields a query term can be scoped to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Name,
+    CommonName,
+    Country,
+    VehicleType,
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Field, String> {
+        Field::parse(s).ok_or(format!(
+            "Unknown field {}, expected name, common_name, country or vehicle_type",
+            s
+        ))
+    }
+}
+
+impl Field {
+    // "country:germany" style prefixes
+    fn parse(prefix: &str) -> Option<Field> {
+        match fold(prefix).as_str() {
+            "name" => Some(Field::Name),
+            "common" | "common_name" | "commonname" => Some(Field::CommonName),
+            "country" => Some(Field::Country),
+            "type" | "vehicle_type" | "vehicletype" => Some(Field::VehicleType),
+            _ => None,
+        }
+    }
+}
+
+// Records a query can run against, owned or borrowed from a response body
+pub trait Searchable {
+    fn values(&self, field: Field) -> Vec<&str>;
+}
+
+impl Searchable for Manufacturer {
+    fn values(&self, field: Field) -> Vec<&str> {
+        match field {
+            Field::Name => self.name.as_deref().into_iter().collect(),
+            Field::CommonName => self.common_name.as_deref().into_iter().collect(),
+            Field::Country => self.country.as_deref().into_iter().collect(),
+            Field::VehicleType => self
+                .vehicle_types
+                .iter()
+                .filter_map(|vehicle_type| vehicle_type.name.as_deref())
+                .collect(),
+        }
+    }
+}
+
+impl Searchable for ManufacturerRef<'_> {
+    fn values(&self, field: Field) -> Vec<&str> {
+        match field {
+            Field::Name => self.name.as_deref().into_iter().collect(),
+            Field::CommonName => self.common_name.as_deref().into_iter().collect(),
+            Field::Country => self.country.as_deref().into_iter().collect(),
+            Field::VehicleType => self
+                .vehicle_types
+                .iter()
+                .filter_map(|vehicle_type| vehicle_type.name.as_deref())
+                .collect(),
+        }
+    }
+}
+
+// Fields searched by a term without a prefix, unless the query says otherwise
+pub const DEFAULT_FIELDS: [Field; 3] = [Field::Name, Field::CommonName, Field::Country];
+
+// One word of a query, optionally scoped to a field
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub field: Option<Field>,
+    pub text: String,
+    // Other names of the manufacturer the text names, normalized, see Query::with_aliases
+    pub aliases: Vec<String>,
+}
+
+impl Term {
+    // Best score of the text or one of its aliases against a field value
+    // Aliases are compared with the normalized value so punctuation and legal forms do not matter
+    fn score(&self, value: &str) -> f64 {
+        let direct = score_value(&self.text, value);
+        if self.aliases.is_empty() {
+            return direct;
+        }
+        let value = normalize(value);
+        self.aliases
+            .iter()
+            .map(|alias| score_value(alias, &value))
+            .fold(direct, f64::max)
+    }
+}
+
+// How well a term matches a single field value, 0.0 meaning no match
+fn score_value(term: &str, value: &str) -> f64 {
+    let value = fold(value);
+    if value == term {
+        return 1.0;
+    }
+    let words: Vec<&str> = value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.contains(&term) {
+        return 0.95;
+    }
+    if words.iter().any(|word| word.starts_with(term)) {
+        return 0.9;
+    }
+    if value.contains(term) {
+        return 0.8;
+    }
+
+    // Typo tolerance grows with the term, short terms have to match exactly
+    let allowed = match term.chars().count() {
+        0..=3 => 0,
+        4..=6 => 1,
+        _ => 2,
+    };
+    words
+        .iter()
+        .map(|word| {
+            let distance = levenshtein(term, word);
+            match distance <= allowed {
+                true => 0.7 * (1.0 - distance as f64 / term.chars().count().max(1) as f64),
+                false => 0.0,
+            }
+        })
+        .fold(0.0, f64::max)
+}
+
+// A parsed search such as `country:germany name:"motoren werke" bmw`
+// Every term has to match, better matches rank higher
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub terms: Vec<Term>,
+    // Searched by terms without a prefix
+    pub fields: Vec<Field>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Query {
+        let mut terms = Vec::new();
+        let mut chars = input.chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+
+            // Read one token, quotes keep spaces together
+            let mut token = String::new();
+            let mut quoted = false;
+            while let Some(c) = chars.next_if(|c| quoted || !c.is_whitespace()) {
+                match c {
+                    '"' => quoted = !quoted,
+                    _ => token.push(c),
+                }
+            }
+
+            // Unknown prefixes are kept as text, "mercedes-benz:" is still a name
+            let term = match token.split_once(':') {
+                Some((prefix, text)) if Field::parse(prefix).is_some() => Term {
+                    field: Field::parse(prefix),
+                    text: fold(text),
+                    aliases: Vec::new(),
+                },
+                _ => Term {
+                    field: None,
+                    text: fold(&token),
+                    aliases: Vec::new(),
+                },
+            };
+            if !term.text.is_empty() {
+                terms.push(term);
+            }
+        }
+        Query {
+            terms,
+            fields: DEFAULT_FIELDS.to_vec(),
+        }
+    }
+
+    // Search other fields than name, common name and country with unprefixed terms
+    // An empty list keeps the defaults
+    pub fn with_fields(mut self, fields: &[Field]) -> Query {
+        if !fields.is_empty() {
+            self.fields = fields.to_vec();
+        }
+        self
+    }
+
+    // Let name terms also match the other names in the alias table,
+    // so "bmw" finds "BAYERISCHE MOTOREN WERKE AG" even without a common name
+    pub fn with_aliases(mut self, aliases: &Aliases) -> Query {
+        for term in &mut self.terms {
+            if matches!(
+                term.field,
+                None | Some(Field::Name) | Some(Field::CommonName)
+            ) {
+                term.aliases = aliases
+                    .names_like(&term.text)
+                    .into_iter()
+                    .filter(|name| *name != term.text)
+                    .map(str::to_string)
+                    .collect();
+            }
+        }
+        self
+    }
+
+    // Relevance of the manufacturer, None when a term does not match
+    pub fn score(&self, manufacturer: &impl Searchable) -> Option<f64> {
+        let mut total = 0.0;
+        for term in &self.terms {
+            let fields = match term.field {
+                Some(field) => vec![field],
+                None => self.fields.clone(),
+            };
+            let best = fields
+                .iter()
+                .flat_map(|field| manufacturer.values(*field))
+                .map(|value| term.score(value))
+                .fold(0.0, f64::max);
+            if best == 0.0 {
+                return None;
+            }
+            total += best;
+        }
+        Some(total / self.terms.len().max(1) as f64)
+    }
+
+    // Matching manufacturers, best first, ties sorted by name
+    pub fn rank<'a>(&self, manufacturers: &'a [Manufacturer]) -> Vec<(&'a Manufacturer, f64)> {
+        self.rank_among(manufacturers.iter())
+    }
+
+    fn rank_among<'a>(
+        &self,
+        manufacturers: impl Iterator<Item = &'a Manufacturer>,
+    ) -> Vec<(&'a Manufacturer, f64)> {
+        let mut ranked: Vec<(&Manufacturer, f64)> = manufacturers
+            .filter_map(|manufacturer| Some((manufacturer, self.score(manufacturer)?)))
+            .collect();
+        ranked.sort_by(|(a, a_score), (b, b_score)| {
+            b_score.total_cmp(a_score).then_with(|| a.name.cmp(&b.name))
+        });
+        ranked
+    }
+}
+
+// Folded words of name, common name and country, pointing at the records holding them
+// A query then scans the vocabulary, much smaller than the records, to find candidates
+pub struct Index {
+    words: HashMap<Field, BTreeMap<String, Vec<usize>>>,
+}
+
+impl Index {
+    pub fn build(manufacturers: &[Manufacturer]) -> Index {
+        let mut words: HashMap<Field, BTreeMap<String, Vec<usize>>> = HashMap::new();
+        for (position, manufacturer) in manufacturers.iter().enumerate() {
+            for field in DEFAULT_FIELDS {
+                for value in manufacturer.values(field) {
+                    for word in fold(value)
+                        .split(|c: char| !c.is_alphanumeric())
+                        .filter(|word| !word.is_empty())
+                    {
+                        let positions = words
+                            .entry(field)
+                            .or_default()
+                            .entry(word.to_string())
+                            .or_default();
+                        if positions.last() != Some(&position) {
+                            positions.push(position);
+                        }
+                    }
+                }
+            }
+        }
+        Index { words }
+    }
+
+    // Records the term can match, None when the index cannot tell
+    // A term without separators only matches inside a single word, so checking
+    // every indexed word finds the same records as scoring every value
+    // Aliases are matched against normalized values, which the words do not cover
+    fn candidates(&self, term: &Term, default_fields: &[Field]) -> Option<BTreeSet<usize>> {
+        if !term.text.chars().all(char::is_alphanumeric) || !term.aliases.is_empty() {
+            return None;
+        }
+        let fields = match term.field {
+            Some(Field::VehicleType) => return None,
+            Some(field) => vec![field],
+            None if default_fields.contains(&Field::VehicleType) => return None,
+            None => default_fields.to_vec(),
+        };
+        let mut found = BTreeSet::new();
+        for words in fields.iter().filter_map(|field| self.words.get(field)) {
+            for (word, positions) in words {
+                if score_value(&term.text, word) > 0.0 {
+                    found.extend(positions);
+                }
+            }
+        }
+        Some(found)
+    }
+
+    // Same results as Query::rank, only scoring the candidates every term agrees on
+    pub fn rank<'a>(
+        &self,
+        query: &Query,
+        manufacturers: &'a [Manufacturer],
+    ) -> Vec<(&'a Manufacturer, f64)> {
+        let candidates = query
+            .terms
+            .iter()
+            .filter_map(|term| self.candidates(term, &query.fields))
+            .reduce(|a, b| a.intersection(&b).copied().collect());
+        match candidates {
+            Some(positions) => query.rank_among(
+                positions
+                    .into_iter()
+                    .filter_map(|position| manufacturers.get(position)),
+            ),
+            None => query.rank(manufacturers),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::now;
+use crate::dirs::app_dir;
+use crate::error::Error;
+use crate::models::Manufacturer;
+
+// Every manufacturer at one point in time, saved as one JSON file
+// Searches can run against it without the network, and two of them can be diffed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub created_at: u64,
+    pub source: String,
+    pub manufacturers: Vec<Manufacturer>,
+}
+
+impl Snapshot {
+    pub fn new(source: impl Into<String>, manufacturers: Vec<Manufacturer>) -> Self {
+        Snapshot {
+            created_at: now(),
+            source: source.into(),
+            manufacturers,
+        }
+    }
+
+    // $XDG_DATA_HOME/manufacturers/snapshot.json, falling back to ~/.local/share
+    pub fn default_path() -> PathBuf {
+        app_dir("XDG_DATA_HOME", &[".local", "share"]).join("snapshot.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Snapshot, Error> {
+        let read_error = |source: io::Error| Error::Io {
+            context: format!("Could not read snapshot {}", path.display()),
+            source,
+        };
+        let content = fs::read_to_string(path).map_err(read_error)?;
+        // A damaged file is bad JSON like a broken response, not a failed read
+        serde_json::from_str(&content).map_err(|source| Error::Decode {
+            url: format!("file://{}", path.display()),
+            source,
+        })
+    }
+
+    // Written to a temporary file first so an interrupted sync keeps the old snapshot
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let write_error = |source: io::Error| Error::Io {
+            context: format!("Could not write snapshot {}", path.display()),
+            source,
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        let content = serde_json::to_string(self).map_err(|e| write_error(e.into()))?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, content).map_err(write_error)?;
+        fs::rename(&temp, path).map_err(write_error)
+    }
+
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.created_at))
+    }
+}
+
+// What changed between two snapshots, records are matched by manufacturer ID
+#[derive(Debug, Default)]
+pub struct Diff<'a> {
+    pub added: Vec<&'a Manufacturer>,
+    pub removed: Vec<&'a Manufacturer>,
+    pub changed: Vec<(&'a Manufacturer, &'a Manufacturer)>,
+}
+
+impl Diff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+pub fn diff<'a>(old: &'a Snapshot, new: &'a Snapshot) -> Diff<'a> {
+    let by_id = |snapshot: &'a Snapshot| -> BTreeMap<u32, &'a Manufacturer> {
+        snapshot
+            .manufacturers
+            .iter()
+            .map(|manufacturer| (manufacturer.id, manufacturer))
+            .collect()
+    };
+    let (old, new) = (by_id(old), by_id(new));
+
+    let mut diff = Diff::default();
+    for (id, before) in &old {
+        match new.get(id) {
+            None => diff.removed.push(before),
+            Some(after) if before != after => diff.changed.push((before, after)),
+            Some(_) => {}
+        }
+    }
+    diff.added = new
+        .iter()
+        .filter(|(id, _)| !old.contains_key(id))
+        .map(|(_, manufacturer)| *manufacturer)
+        .collect();
+    diff
+}
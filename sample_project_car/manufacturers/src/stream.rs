@@ -0,0 +1,187 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+
+use crate::models::{Manufacturer, VehicleType};
+
+// Streaming access to the Results array of a vPIC body
+// Records are handed over one at a time instead of collecting the whole page first,
+// so a caller keeping only a few of them never holds the rest in memory
+
+// Call `f` with every record of the Results array, returns how many there were
+// The other envelope fields are skipped without being parsed into values
+pub fn for_each_result<'de, T, F>(body: &'de str, mut f: F) -> Result<usize, serde_json::Error>
+where
+    T: Deserialize<'de>,
+    F: FnMut(T),
+{
+    let mut deserializer = serde_json::Deserializer::from_str(body);
+    let count = deserializer.deserialize_map(Envelope {
+        f: &mut f,
+        marker: PhantomData,
+    })?;
+    deserializer.end()?;
+    Ok(count)
+}
+
+// The records of the Results array for which `keep` is true, and the number of records seen
+pub fn filter_results<'de, T, F>(
+    body: &'de str,
+    mut keep: F,
+) -> Result<(Vec<T>, usize), serde_json::Error>
+where
+    T: Deserialize<'de>,
+    F: FnMut(&T) -> bool,
+{
+    let mut kept = Vec::new();
+    let count = for_each_result(body, |record: T| {
+        if keep(&record) {
+            kept.push(record);
+        }
+    })?;
+    Ok((kept, count))
+}
+
+struct Envelope<'f, T, F> {
+    f: &'f mut F,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T, F> Visitor<'de> for Envelope<'_, T, F>
+where
+    T: Deserialize<'de>,
+    F: FnMut(T),
+{
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a vPIC response object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<usize, A::Error> {
+        let mut count = None;
+        while let Some(key) = map.next_key::<Cow<'de, str>>()? {
+            match key.as_ref() {
+                "Results" => {
+                    count = Some(map.next_value_seed(Results {
+                        f: &mut *self.f,
+                        marker: PhantomData,
+                    })?)
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        count.ok_or_else(|| de::Error::missing_field("Results"))
+    }
+}
+
+struct Results<'f, T, F> {
+    f: &'f mut F,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T, F> DeserializeSeed<'de> for Results<'_, T, F>
+where
+    T: Deserialize<'de>,
+    F: FnMut(T),
+{
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T, F> Visitor<'de> for Results<'_, T, F>
+where
+    T: Deserialize<'de>,
+    F: FnMut(T),
+{
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of results")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+        let mut count = 0;
+        while let Some(record) = seq.next_element::<T>()? {
+            (self.f)(record);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+// Optional string borrowed from the body, only copied when it contains escapes
+fn borrowed_text<'de: 'a, 'a, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Cow<'a, str>>, D::Error> {
+    #[derive(Deserialize)]
+    struct Text<'a>(#[serde(borrow)] Cow<'a, str>);
+    Ok(Option::<Text>::deserialize(deserializer)?.map(|text| text.0))
+}
+
+// A manufacturer record pointing into the response body instead of owning its strings
+// Cheap to build and drop for the records a search throws away
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManufacturerRef<'a> {
+    #[serde(rename = "Mfr_ID")]
+    pub id: u32,
+    #[serde(
+        rename = "Mfr_Name",
+        borrow,
+        default,
+        deserialize_with = "borrowed_text"
+    )]
+    pub name: Option<Cow<'a, str>>,
+    #[serde(
+        rename = "Mfr_CommonName",
+        borrow,
+        default,
+        deserialize_with = "borrowed_text"
+    )]
+    pub common_name: Option<Cow<'a, str>>,
+    #[serde(
+        rename = "Country",
+        borrow,
+        default,
+        deserialize_with = "borrowed_text"
+    )]
+    pub country: Option<Cow<'a, str>>,
+    #[serde(rename = "VehicleTypes", borrow, default)]
+    pub vehicle_types: Vec<VehicleTypeRef<'a>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct VehicleTypeRef<'a> {
+    pub is_primary: bool,
+    #[serde(borrow, default, deserialize_with = "borrowed_text")]
+    pub name: Option<Cow<'a, str>>,
+}
+
+impl ManufacturerRef<'_> {
+    pub fn to_manufacturer(&self) -> Manufacturer {
+        let text = |value: &Option<Cow<str>>| value.as_deref().map(str::to_string);
+        Manufacturer {
+            id: self.id,
+            name: text(&self.name),
+            common_name: text(&self.common_name),
+            country: text(&self.country),
+            vehicle_types: self
+                .vehicle_types
+                .iter()
+                .map(|vehicle_type| VehicleType {
+                    is_primary: vehicle_type.is_primary,
+                    name: text(&vehicle_type.name),
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::alias::{normalize, Aliases};
+use crate::models::Manufacturer;
+
+// Vehicle Identification Number checks that need no network
+// Layout (ISO 3779): WMI (1-3), VDS (4-9, the 9th being the check digit), VIS (10-17)
+
+pub const VIN_LENGTH: usize = 17;
+
+// Weight of every position in the check digit sum
+const WEIGHTS: [u32; VIN_LENGTH] = [8, 7, 6, 5, 4, 3, 2, 10, 0, 9, 8, 7, 6, 5, 4, 3, 2];
+
+// Model year codes in position 10, repeating every 30 years from 1980
+const YEAR_CODES: &str = "ABCDEFGHJKLMNPRSTVWXY123456789";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VinError {
+    Length(usize),
+    InvalidChar { position: usize, found: char },
+    CheckDigit { expected: char, found: char },
+}
+
+impl fmt::Display for VinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VinError::Length(length) => {
+                write!(f, "a VIN has {} characters, got {}", VIN_LENGTH, length)
+            }
+            VinError::InvalidChar { position, found } => {
+                write!(f, "invalid character '{}' at position {}", found, position)
+            }
+            VinError::CheckDigit { expected, found } => write!(
+                f,
+                "check digit is '{}' but the VIN computes to '{}'",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl Error for VinError {}
+
+// Numeric value of a VIN character, I, O and Q are not allowed
+fn transliterate(c: char) -> Option<u32> {
+    match c {
+        '0'..='9' => c.to_digit(10),
+        'A'..='H' => Some(c as u32 - 'A' as u32 + 1),
+        'J'..='N' => Some(c as u32 - 'J' as u32 + 1),
+        'P' => Some(7),
+        'R' => Some(9),
+        'S'..='Z' => Some(c as u32 - 'S' as u32 + 2),
+        _ => None,
+    }
+}
+
+// Expected check digit of a 17 character VIN, None if it has invalid characters
+pub fn check_digit(vin: &str) -> Option<char> {
+    if vin.chars().count() != VIN_LENGTH {
+        return None;
+    }
+    let mut sum = 0;
+    for (c, weight) in vin.chars().zip(WEIGHTS) {
+        sum += transliterate(c)? * weight;
+    }
+    match sum % 11 {
+        10 => Some('X'),
+        remainder => char::from_digit(remainder, 10),
+    }
+}
+
+// A VIN that passed the length, character and check digit checks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vin {
+    code: String,
+}
+
+impl Vin {
+    // Validate a VIN, surrounding spaces and lowercase letters are accepted
+    pub fn parse(input: &str) -> Result<Vin, VinError> {
+        let vin = Vin::parse_structure(input)?;
+        let expected = check_digit(&vin.code).unwrap_or_default();
+        let found = vin.check_digit();
+        if expected != found {
+            return Err(VinError::CheckDigit { expected, found });
+        }
+        Ok(vin)
+    }
+
+    // Only check length and characters
+    // Outside North America the 9th character is often not a check digit
+    pub fn parse_structure(input: &str) -> Result<Vin, VinError> {
+        let code = input.trim().to_uppercase();
+        let length = code.chars().count();
+        if length != VIN_LENGTH {
+            return Err(VinError::Length(length));
+        }
+        for (index, c) in code.chars().enumerate() {
+            if transliterate(c).is_none() {
+                return Err(VinError::InvalidChar {
+                    position: index + 1,
+                    found: c,
+                });
+            }
+        }
+        Ok(Vin { code })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.code
+    }
+
+    // Indexing by byte is fine, only ASCII gets through parsing
+
+    // World Manufacturer Identifier, the first three characters
+    pub fn wmi(&self) -> &str {
+        &self.code[..3]
+    }
+
+    // Vehicle Descriptor Section, positions 4 to 9 including the check digit
+    pub fn vds(&self) -> &str {
+        &self.code[3..9]
+    }
+
+    // Vehicle Identifier Section, positions 10 to 17
+    pub fn vis(&self) -> &str {
+        &self.code[9..]
+    }
+
+    pub fn check_digit(&self) -> char {
+        self.code.as_bytes()[8] as char
+    }
+
+    pub fn model_year_code(&self) -> char {
+        self.code.as_bytes()[9] as char
+    }
+
+    // Model year using the 30-year cycle rule for cars, MPVs and light trucks:
+    // a digit in position 7 means 1980-2009, a letter means 2010-2039
+    pub fn model_year(&self) -> Option<u16> {
+        let offset = YEAR_CODES.find(self.model_year_code())? as u16;
+        let cycle_start = match self.code.as_bytes()[6].is_ascii_digit() {
+            true => 1980,
+            false => 2010,
+        };
+        Some(cycle_start + offset)
+    }
+}
+
+impl fmt::Display for Vin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code)
+    }
+}
+
+// Continent a WMI was assigned to, from its first character
+pub fn region(wmi: &str) -> Option<&'static str> {
+    match wmi.chars().next()? {
+        'A'..='H' => Some("Africa"),
+        'J'..='R' => Some("Asia"),
+        'S'..='Z' => Some("Europe"),
+        '1'..='5' => Some("North America"),
+        '6' | '7' => Some("Oceania"),
+        '8' | '9' | '0' => Some("South America"),
+        _ => None,
+    }
+}
+
+// A known World Manufacturer Identifier
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WmiEntry {
+    pub wmi: &'static str,
+    pub make: &'static str,
+    pub country: &'static str,
+}
+
+impl WmiEntry {
+    // Manufacturer records of the make: those whose name or common name mentions it,
+    // and those registered under one of its aliases, e.g. BAYERISCHE MOTOREN WERKE AG for BMW
+    pub fn find_in<'a>(
+        &self,
+        aliases: &Aliases,
+        manufacturers: &'a [Manufacturer],
+    ) -> Vec<&'a Manufacturer> {
+        let make = normalize(self.make);
+        let key = aliases
+            .canonical(self.make)
+            .map(normalize)
+            .unwrap_or_else(|| make.clone());
+        // Whole words only, so Kia does not match NIKIAS
+        let mentions =
+            |name: &str| format!(" {} ", normalize(name)).contains(&format!(" {} ", make));
+        manufacturers
+            .iter()
+            .filter(|manufacturer| {
+                aliases.key(manufacturer) == key
+                    || [&manufacturer.name, &manufacturer.common_name]
+                        .iter()
+                        .filter_map(|name| name.as_deref())
+                        .any(mentions)
+            })
+            .collect()
+    }
+}
+
+// Common WMIs as (wmi, make, country), not exhaustive, vPIC has the full list
+const WMI_TABLE: &[(&str, &str, &str)] = &[
+    ("1FA", "Ford", "United States"),
+    ("1FM", "Ford", "United States"),
+    ("1FT", "Ford", "United States"),
+    ("1G1", "Chevrolet", "United States"),
+    ("1GC", "Chevrolet", "United States"),
+    ("1G6", "Cadillac", "United States"),
+    ("1HG", "Honda", "United States"),
+    ("1J4", "Jeep", "United States"),
+    ("1N4", "Nissan", "United States"),
+    ("1VW", "Volkswagen", "United States"),
+    ("2HG", "Honda", "Canada"),
+    ("2T1", "Toyota", "Canada"),
+    ("3VW", "Volkswagen", "Mexico"),
+    ("4T1", "Toyota", "United States"),
+    ("5UX", "BMW", "United States"),
+    ("5YJ", "Tesla", "United States"),
+    ("JHM", "Honda", "Japan"),
+    ("JM1", "Mazda", "Japan"),
+    ("JN1", "Nissan", "Japan"),
+    ("JTD", "Toyota", "Japan"),
+    ("KMH", "Hyundai", "South Korea"),
+    ("KNA", "Kia", "South Korea"),
+    ("SAJ", "Jaguar", "United Kingdom"),
+    ("SAL", "Land Rover", "United Kingdom"),
+    ("SCC", "Lotus", "United Kingdom"),
+    ("VF1", "Renault", "France"),
+    ("VF3", "Peugeot", "France"),
+    ("WAU", "Audi", "Germany"),
+    ("WBA", "BMW", "Germany"),
+    ("WBS", "BMW", "Germany"),
+    ("WDB", "Mercedes-Benz", "Germany"),
+    ("WDD", "Mercedes-Benz", "Germany"),
+    ("WMW", "MINI", "Germany"),
+    ("WP0", "Porsche", "Germany"),
+    ("WVG", "Volkswagen", "Germany"),
+    ("WVW", "Volkswagen", "Germany"),
+    ("YV1", "Volvo", "Sweden"),
+    ("ZAR", "Alfa Romeo", "Italy"),
+    ("ZFA", "Fiat", "Italy"),
+    ("ZFF", "Ferrari", "Italy"),
+];
+
+pub fn lookup_wmi(wmi: &str) -> Option<WmiEntry> {
+    let wmi = wmi.to_uppercase();
+    WMI_TABLE
+        .iter()
+        .find(|(known, _, _)| *known == wmi)
+        .map(|&(wmi, make, country)| WmiEntry { wmi, make, country })
+}
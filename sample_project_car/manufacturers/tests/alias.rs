@@ -0,0 +1,141 @@
+// Name normalization and folding the legal names of one manufacturer together
+mod common;
+
+use std::fs;
+
+use common::temp_dir;
+use manufacturers::alias::{normalize, Aliases};
+use manufacturers::{Error, Manufacturer};
+
+fn manufacturer(id: u32, name: Option<&str>) -> Manufacturer {
+    Manufacturer {
+        id,
+        name: name.map(str::to_string),
+        common_name: None,
+        country: None,
+        vehicle_types: Vec::new(),
+    }
+}
+
+#[test]
+fn legal_suffixes_are_dropped() {
+    let cases = [
+        ("BAYERISCHE MOTOREN WERKE AG", "bayerische motoren werke"),
+        ("Ford Motor Company", "ford motor"),
+        ("TESLA, INC.", "tesla"),
+        // Several legal forms in a row all go
+        ("HONDA MOTOR CO., LTD.", "honda motor"),
+        ("Volkswagen GmbH & Co. KG", "volkswagen"),
+        // Only at the end of the name
+        ("AG CHEM EQUIPMENT", "ag chem equipment"),
+        // A name made only of a legal form is kept
+        ("S.A.", "sa"),
+        ("Company", "company"),
+    ];
+    for (name, normalized) in cases {
+        assert_eq!(normalize(name), normalized, "{}", name);
+    }
+}
+
+#[test]
+fn punctuation_and_accents_are_normalized() {
+    let cases = [
+        ("Bayerische Motoren-Werke", "bayerische motoren werke"),
+        ("AUTOMOBILES CITROËN", "automobiles citroen"),
+        // Dots and apostrophes keep their letters together
+        ("O'BRIEN TRAILERS", "obrien trailers"),
+        ("M.A.N. TRUCK & BUS", "man truck bus"),
+        ("  Mercedes-Benz  (USA) ", "mercedes benz usa"),
+        ("", ""),
+        (" - ", ""),
+    ];
+    for (name, normalized) in cases {
+        assert_eq!(normalize(name), normalized, "{:?}", name);
+    }
+}
+
+#[test]
+fn builtin_names_share_a_canonical_name() {
+    let aliases = Aliases::builtin();
+    assert_eq!(
+        aliases.canonical("BAYERISCHE MOTOREN WERKE AG"),
+        Some("BMW")
+    );
+    assert_eq!(aliases.canonical("bmw"), Some("BMW"));
+    assert_eq!(aliases.canonical("Honda Motor Co., Ltd."), Some("Honda"));
+    assert_eq!(aliases.canonical("FORDSON"), None);
+
+    assert_eq!(
+        aliases.names_like("BMW AG"),
+        ["bayerische motoren werke", "bmw"]
+    );
+    assert!(aliases.names_like("FORDSON").is_empty());
+}
+
+#[test]
+fn records_are_keyed_by_canonical_or_normalized_name() {
+    let aliases = Aliases::builtin();
+    let key = |name| aliases.key(&manufacturer(1, name));
+    assert_eq!(key(Some("BAYERISCHE MOTOREN WERKE AG")), "bmw");
+    assert_eq!(key(Some("Tesla Motors")), "tesla");
+    assert_eq!(key(Some("FORDSON TRACTORS LTD")), "fordson tractors");
+    assert_eq!(key(None), "");
+}
+
+#[test]
+fn dedup_keeps_the_first_record_of_each_manufacturer() {
+    let manufacturers = [
+        manufacturer(3, Some("TESLA, INC.")),
+        manufacturer(1, Some("BAYERISCHE MOTOREN WERKE AG")),
+        manufacturer(5, None),
+        manufacturer(2, Some("BMW AG")),
+        manufacturer(6, None),
+        manufacturer(4, Some("Tesla Motors")),
+        manufacturer(7, Some("FORDSON TRACTORS")),
+        manufacturer(8, Some("Fordson Tractors Ltd.")),
+    ];
+    let ids: Vec<u32> = Aliases::builtin()
+        .dedup(manufacturers.iter().collect())
+        .iter()
+        .map(|manufacturer| manufacturer.id)
+        .collect();
+    // Records without a name are never folded together
+    assert_eq!(ids, [3, 1, 5, 6, 7]);
+}
+
+#[test]
+fn aliases_files_extend_and_override_the_builtin_table() {
+    let dir = temp_dir("aliases-load");
+    let path = dir.join("aliases");
+    fs::write(
+        &path,
+        "# local names\n\nAcme = Acme Motors, ACME TRUCKS LTD\nBavaria = BMW # nickname\n",
+    )
+    .unwrap();
+    let aliases = Aliases::load(&path).unwrap();
+
+    assert_eq!(aliases.canonical("Acme Trucks"), Some("Acme"));
+    assert_eq!(aliases.canonical("acme"), Some("Acme"));
+    assert_eq!(aliases.canonical("BMW AG"), Some("Bavaria"));
+    // The rest of the built-in table stays
+    assert_eq!(aliases.canonical("Daimler"), Some("Mercedes-Benz"));
+}
+
+#[test]
+fn broken_aliases_files_are_rejected() {
+    let dir = temp_dir("aliases-broken");
+    let path = dir.join("aliases");
+    fs::write(&path, "Acme = Acme Motors\nno equals sign here\n").unwrap();
+    let error = Aliases::load(&path).unwrap_err();
+    assert!(matches!(error, Error::InvalidInput(_)), "{}", error);
+    assert!(error.to_string().contains("line 2"), "{}", error);
+
+    fs::write(&path, " = nameless\n").unwrap();
+    assert!(matches!(
+        Aliases::load(&path).unwrap_err(),
+        Error::InvalidInput(_)
+    ));
+
+    let error = Aliases::load(&dir.join("missing")).unwrap_err();
+    assert!(matches!(error, Error::Io { .. }), "{}", error);
+}
@@ -0,0 +1,119 @@
+// The response cache: freshness, revalidation and what happens when it cannot be used
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use common::{temp_dir, MockServer, Reply};
+use manufacturers::cache::Cache;
+use manufacturers::retry::RetryPolicy;
+use manufacturers::{Error, VpicClient};
+
+const ETAG: &str = "\"makes-v1\"";
+const LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+fn client(server: &MockServer, cache_dir: &Path, ttl: Duration) -> VpicClient {
+    VpicClient::with_base_url(&server.base_url)
+        .with_retry(RetryPolicy::none())
+        .with_cache(Cache::new(cache_dir, ttl))
+}
+
+#[tokio::test]
+async fn fresh_entries_are_answered_from_disk() {
+    let server = MockServer::start(vec![("", Reply::Fixture("makes_bmw.json"))]).await;
+    let dir = temp_dir("cache-fresh");
+    let client = client(&server, &dir, Duration::from_secs(60));
+
+    let first = client.makes_for_manufacturer("bmw").await.unwrap();
+    let second = client.makes_for_manufacturer("bmw").await.unwrap();
+
+    assert!(!first.is_empty());
+    assert_eq!(first.len(), second.len());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn expired_entries_are_revalidated_with_their_validators() {
+    let server = MockServer::scripted(
+        vec![
+            Reply::Tagged("makes_bmw.json", ETAG, LAST_MODIFIED),
+            Reply::Status(304, None),
+        ],
+        Vec::new(),
+    )
+    .await;
+    let dir = temp_dir("cache-revalidate");
+    // A TTL of zero makes every entry stale right away
+    let client = client(&server, &dir, Duration::ZERO);
+
+    let first = client.makes_for_manufacturer("bmw").await.unwrap();
+    let second = client.makes_for_manufacturer("bmw").await.unwrap();
+
+    assert!(!first.is_empty());
+    assert_eq!(first.len(), second.len());
+    let heads = server.heads();
+    assert_eq!(heads.len(), 2);
+    let revalidation = heads[1].to_lowercase();
+    assert!(revalidation.contains(&format!("if-none-match: {}", ETAG.to_lowercase())));
+    assert!(revalidation.contains(&format!(
+        "if-modified-since: {}",
+        LAST_MODIFIED.to_lowercase()
+    )));
+    assert!(!heads[0].to_lowercase().contains("if-none-match"));
+}
+
+#[tokio::test]
+async fn expired_entries_are_replaced_by_a_new_body() {
+    let server = MockServer::scripted(
+        vec![
+            Reply::Tagged("makes_bmw.json", ETAG, LAST_MODIFIED),
+            Reply::Fixture("empty.json"),
+        ],
+        Vec::new(),
+    )
+    .await;
+    let dir = temp_dir("cache-expired");
+    let client = client(&server, &dir, Duration::ZERO);
+
+    assert!(!client
+        .makes_for_manufacturer("bmw")
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(client
+        .makes_for_manufacturer("bmw")
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn not_modified_without_a_cached_body_is_a_status_error() {
+    let server = MockServer::start(vec![("", Reply::Status(304, None))]).await;
+    let dir = temp_dir("cache-304");
+    let error = client(&server, &dir, Duration::from_secs(60))
+        .makes_for_manufacturer("bmw")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, Error::Status { .. }), "{}", error);
+    assert_eq!(error.exit_code(), 4);
+}
+
+#[tokio::test]
+async fn unwritable_cache_does_not_fail_the_request() {
+    let server = MockServer::start(vec![("", Reply::Fixture("makes_bmw.json"))]).await;
+    let dir = temp_dir("cache-unwritable");
+    // A file where the cache directory should be
+    let blocked = dir.join("cache");
+    fs::write(&blocked, "").unwrap();
+
+    let makes = client(&server, &blocked, Duration::from_secs(60))
+        .makes_for_manufacturer("bmw")
+        .await
+        .unwrap();
+
+    assert!(!makes.is_empty());
+}
@@ -0,0 +1,896 @@
+// The manufacturers binary end to end against the fixture server
+mod common;
+
+use common::{
+    run, run_into_closed_pipe, run_with_input, run_without_base_url, run_without_xdg, stderr,
+    stdout, temp_dir, MockServer, Reply,
+};
+use serde_json::Value;
+
+async fn paginated() -> MockServer {
+    MockServer::start(vec![
+        (
+            "getallmanufacturers?format=json&page=1$",
+            Reply::Fixture("manufacturers_page1.json"),
+        ),
+        (
+            "getallmanufacturers?format=json&page=2$",
+            Reply::Fixture("manufacturers_page2.json"),
+        ),
+        ("getallmanufacturers", Reply::Fixture("empty.json")),
+        ("GetMakeForManufacturer/", Reply::Fixture("makes_bmw.json")),
+        (
+            "GetModelsForMakeYear/",
+            Reply::Fixture("models_bmw_2020.json"),
+        ),
+        ("DecodeVinValues/", Reply::Fixture("decode_vin.json")),
+    ])
+    .await
+}
+
+#[tokio::test]
+async fn search_finds_matches_on_every_page() {
+    let server = paginated().await;
+    let home = temp_dir("search-pages");
+    let output = run(&server, &home, &["bmw", "--no-cache"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.starts_with("Found 2 manufacturers: "));
+    assert!(stdout.contains("BAYERISCHE MOTOREN WERKE AG"));
+    assert!(stdout.contains("BMW OF NORTH AMERICA, LLC"));
+    assert!(!stderr(&output).contains("Warning"));
+}
+
+#[tokio::test]
+async fn max_pages_cutting_the_download_short_warns() {
+    let server = paginated().await;
+    let home = temp_dir("search-max-pages");
+    let output = run(
+        &server,
+        &home,
+        &[
+            "bmw",
+            "--no-cache",
+            "--max-pages",
+            "1",
+            "--concurrency",
+            "18446744073709551615",
+        ],
+    )
+    .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).starts_with("Found 1 manufacturers: "));
+    assert!(stderr(&output).contains("Warning: stopped after 1 pages"));
+}
+
+#[tokio::test]
+async fn input_runs_a_search_per_line() {
+    let server = paginated().await;
+    let home = temp_dir("batch-search");
+    let keywords = "# makes to look for\nbmw\n\n  tesla  \nnothing like this\n";
+    let output = run_with_input(&server, &home, &["--input", "-", "--no-cache"], keywords).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    let headers: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.starts_with("== "))
+        .collect();
+    assert_eq!(
+        headers,
+        [
+            "== bmw: 2 manufacturers ==",
+            "== tesla: 1 manufacturers ==",
+            "== nothing like this: 0 manufacturers ==",
+        ]
+    );
+    let bmw = stdout.find("== bmw").unwrap();
+    let tesla = stdout.find("== tesla").unwrap();
+    assert!(stdout[bmw..tesla].contains("BMW OF NORTH AMERICA, LLC"));
+    assert!(stdout[tesla..].contains("TESLA, INC."));
+    assert!(stdout.ends_with("1 of 3 queries found nothing: nothing like this\n"));
+    // The whole input is answered from one download, no page is fetched twice
+    let mut pages: Vec<String> = server
+        .requests()
+        .into_iter()
+        .filter(|request| request.contains("getallmanufacturers"))
+        .collect();
+    let fetched = pages.len();
+    pages.sort();
+    pages.dedup();
+    assert_eq!(pages.len(), fetched);
+}
+
+#[tokio::test]
+async fn input_with_structured_output_summarises_on_stderr() {
+    let server = paginated().await;
+    let home = temp_dir("batch-search-csv");
+    let output = run_with_input(
+        &server,
+        &home,
+        &[
+            "--input",
+            "-",
+            "--no-cache",
+            "--output",
+            "csv",
+            "--columns",
+            "query,id",
+        ],
+        "bmw\nnothing like this\nnor this\n",
+    )
+    .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert_eq!(stdout.lines().next(), Some("query,id"));
+    assert_eq!(
+        stdout
+            .lines()
+            .filter(|line| line.starts_with("bmw,"))
+            .count(),
+        2
+    );
+    assert!(!stdout.contains("found nothing"));
+    assert_eq!(
+        stderr(&output).trim_end(),
+        "2 of 3 queries found nothing: nothing like this, nor this"
+    );
+}
+
+#[tokio::test]
+async fn input_without_any_hit_exits_with_1() {
+    let server = paginated().await;
+    let home = temp_dir("batch-search-none");
+    let output = run_with_input(
+        &server,
+        &home,
+        &["--input", "-", "--no-cache"],
+        "nothing like this\n",
+    )
+    .await;
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("1 of 1 queries found nothing: nothing like this"));
+
+    let output = run_with_input(&server, &home, &["--input", "-"], "# only a comment\n").await;
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[tokio::test]
+async fn repl_shows_details_by_id_and_rejects_empty_queries() {
+    let server = MockServer::start(vec![
+        (
+            "getallmanufacturers?format=json&page=1$",
+            Reply::Fixture("manufacturers_page1.json"),
+        ),
+        ("getallmanufacturers", Reply::Fixture("empty.json")),
+        (
+            "GetManufacturerDetails/968?",
+            Reply::Fixture("manufacturer_details_bmw.json"),
+        ),
+    ])
+    .await;
+    let home = temp_dir("repl");
+    let output = run_with_input(
+        &server,
+        &home,
+        &["repl", "--no-cache"],
+        "name:\n:show 968\n:quit\n",
+    )
+    .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("Error: Type a search term"), "{}", stdout);
+    assert!(!stdout.contains("Found "));
+    assert!(!stdout.contains("no contact details"), "{}", stdout);
+    assert!(server
+        .requests()
+        .iter()
+        .any(|request| request.contains("GetManufacturerDetails/968?")));
+}
+
+#[tokio::test]
+async fn search_ignores_accents() {
+    let server = paginated().await;
+    let home = temp_dir("search-accents");
+    let output = run(
+        &server,
+        &home,
+        &[
+            "citroen",
+            "--no-cache",
+            "--output",
+            "csv",
+            "--columns",
+            "id",
+        ],
+    )
+    .await;
+
+    assert_eq!(stdout(&output), "id\n1120\n");
+}
+
+#[tokio::test]
+async fn json_output_has_stable_fields() {
+    let server = paginated().await;
+    let home = temp_dir("search-json");
+    let output = run(
+        &server,
+        &home,
+        &["country:germany", "--no-cache", "--output", "json"],
+    )
+    .await;
+
+    let records: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    let keys: Vec<&String> = records[0].as_object().unwrap().keys().collect();
+    assert_eq!(
+        keys,
+        [
+            "common_name",
+            "country",
+            "id",
+            "name",
+            "primary_vehicle_type",
+            "vehicle_types"
+        ]
+    );
+    assert_eq!(records[0]["vehicle_types"][1], "Motorcycle");
+}
+
+#[tokio::test]
+async fn no_match_exits_with_1() {
+    let server = paginated().await;
+    let home = temp_dir("search-none");
+    let output = run(&server, &home, &["zzzz", "--no-cache"]).await;
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("No manufacturers found"));
+}
+
+#[tokio::test]
+async fn empty_dataset_exits_with_1() {
+    let server = MockServer::start(vec![("", Reply::Fixture("empty.json"))]).await;
+    let home = temp_dir("search-empty");
+    let output = run(&server, &home, &["bmw", "--no-cache"]).await;
+
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[tokio::test]
+async fn malformed_json_exits_with_5() {
+    let server = MockServer::start(vec![("", Reply::Fixture("truncated.json"))]).await;
+    let home = temp_dir("search-malformed");
+    let output = run(&server, &home, &["bmw", "--no-cache"]).await;
+
+    assert_eq!(output.status.code(), Some(5));
+    assert!(stderr(&output).contains("Unexpected response"));
+}
+
+#[tokio::test]
+async fn http_errors_exit_with_4() {
+    let server = MockServer::start(vec![("", Reply::Status(500, None))]).await;
+    let home = temp_dir("search-500");
+    let output = run(&server, &home, &["bmw", "--no-cache"]).await;
+
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[tokio::test]
+async fn unknown_options_exit_with_2() {
+    let server = paginated().await;
+    let home = temp_dir("bad-option");
+    let output = run(&server, &home, &["bmw", "--colour"]).await;
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn out_of_range_numbers_exit_with_2() {
+    let server = paginated().await;
+    let home = temp_dir("out-of-range");
+    let cases: [&[&str]; 4] = [
+        &["models", "--make", "bmw", "--year", "70000"],
+        &["models", "--make", "bmw", "--year", "0"],
+        &["bmw", "--retries", "4294967296"],
+        &["bmw", "--timeout", "-1"],
+    ];
+    for args in cases {
+        let output = run(&server, &home, args).await;
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(stderr(&output).contains(args[args.len() - 2]), "{:?}", args);
+    }
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn offline_search_uses_the_cache() {
+    let server = paginated().await;
+    let home = temp_dir("offline");
+
+    let missing = run(&server, &home, &["bmw", "--offline"]).await;
+    assert_eq!(missing.status.code(), Some(6));
+
+    let online = run(&server, &home, &["bmw"]).await;
+    assert!(online.status.success());
+    let requests = server.requests().len();
+
+    let offline = run(&server, &home, &["bmw", "--offline"]).await;
+    assert!(offline.status.success(), "{}", stderr(&offline));
+    assert_eq!(stdout(&offline), stdout(&online));
+    assert_eq!(server.requests().len(), requests);
+}
+
+#[tokio::test]
+async fn offline_search_needs_no_pages_past_the_end() {
+    let server = paginated().await;
+    let home = temp_dir("offline-concurrency");
+
+    // Caches pages 1 to 3, the third is the empty one
+    let online = run(&server, &home, &["bmw", "--concurrency", "1"]).await;
+    assert!(online.status.success(), "{}", stderr(&online));
+
+    // Asks for pages 1 to 8 at once, 4 to 8 were never cached
+    let offline = run(&server, &home, &["bmw", "--offline", "--concurrency", "8"]).await;
+    assert!(offline.status.success(), "{}", stderr(&offline));
+    assert_eq!(stdout(&offline), stdout(&online));
+}
+
+#[tokio::test]
+async fn snapshot_search_needs_no_server() {
+    let server = paginated().await;
+    let home = temp_dir("snapshot");
+
+    let sync = run(&server, &home, &["sync"]).await;
+    assert!(sync.status.success(), "{}", stderr(&sync));
+    assert!(stdout(&sync).starts_with("Saved 5 manufacturers"));
+    let requests = server.requests().len();
+
+    let output = run(
+        &server,
+        &home,
+        &["tesla", "--output", "csv", "--columns", "name"],
+    )
+    .await;
+    assert_eq!(stdout(&output), "name\n\"TESLA, INC.\"\n");
+    assert_eq!(server.requests().len(), requests);
+    // Said on stderr whatever the format
+    assert!(
+        stderr(&output).starts_with(&format!("Using the snapshot of {} from ", server.base_url))
+    );
+}
+
+#[tokio::test]
+async fn snapshots_of_another_base_url_are_not_searched() {
+    let server = paginated().await;
+    let home = temp_dir("snapshot-other-url");
+    let sync = run(&server, &home, &["sync"]).await;
+    assert!(sync.status.success(), "{}", stderr(&sync));
+
+    let other = paginated().await;
+    let output = run(&other, &home, &["tesla", "--output", "json"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!stderr(&output).contains("snapshot"));
+    assert!(!other.requests().is_empty());
+
+    // Nothing listens there, so the snapshot must not answer in its place
+    let output = run_without_base_url(
+        &home,
+        &["tesla", "--no-cache", "--base-url", "http://127.0.0.1:1"],
+    )
+    .await;
+    assert_eq!(output.status.code(), Some(3), "{}", stderr(&output));
+
+    // Unless it is asked for
+    let path = home.join("data/manufacturers/snapshot.json");
+    let output = run(
+        &other,
+        &home,
+        &["tesla", "--snapshot", path.to_str().unwrap()],
+    )
+    .await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains(&server.base_url));
+}
+
+#[tokio::test]
+async fn profiles_switch_the_base_url_with_a_snapshot_present() {
+    let production = paginated().await;
+    let mirror = paginated().await;
+    let home = temp_dir("snapshot-profile");
+    let dir = home.join("config").join("manufacturers");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        format!(
+            "[profiles.default]\nbase_url = \"{}\"\n\n[profiles.mirror]\nbase_url = \"{}\"\n",
+            production.base_url, mirror.base_url
+        ),
+    )
+    .unwrap();
+
+    let sync = run_without_base_url(&home, &["sync"]).await;
+    assert!(sync.status.success(), "{}", stderr(&sync));
+    let synced = production.requests().len();
+
+    let output = run_without_base_url(&home, &["tesla"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Using the snapshot"));
+    assert_eq!(production.requests().len(), synced);
+
+    let output = run_without_base_url(&home, &["tesla", "--profile", "mirror"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!stderr(&output).contains("snapshot"));
+    assert!(!mirror.requests().is_empty());
+    assert_eq!(production.requests().len(), synced);
+}
+
+#[tokio::test]
+async fn files_default_to_the_home_directory_without_xdg() {
+    let server = paginated().await;
+    let home = temp_dir("no-xdg");
+    let config = home.join(".config").join("manufacturers");
+    std::fs::create_dir_all(&config).unwrap();
+    std::fs::write(config.join("aliases"), "Bavaria = BMW\n").unwrap();
+    std::fs::write(
+        config.join("config.toml"),
+        "[profiles.default]\noutput = \"csv\"\n",
+    )
+    .unwrap();
+
+    let sync = run_without_xdg(&server, &home, &["sync"]).await;
+    assert!(sync.status.success(), "{}", stderr(&sync));
+    assert!(home
+        .join(".local/share/manufacturers/snapshot.json")
+        .is_file());
+
+    // The config picks csv, the aliases file knows Bavaria
+    let output = run_without_xdg(&server, &home, &["bavaria", "--columns", "id"]).await;
+    assert_eq!(stdout(&output), "id\n968\n4108\n");
+
+    // Sync always downloads, other lookups go through the cache
+    let makes = run_without_xdg(&server, &home, &["makes", "--manufacturer", "bmw"]).await;
+    assert!(makes.status.success(), "{}", stderr(&makes));
+    assert!(std::fs::read_dir(home.join(".cache/manufacturers"))
+        .unwrap()
+        .next()
+        .is_some());
+}
+
+#[tokio::test]
+async fn diff_lists_what_changed_between_snapshots() {
+    let server = paginated().await;
+    let home = temp_dir("diff");
+    let snapshot = |name: &str, manufacturers: serde_json::Value| {
+        let path = home.join(name);
+        let snapshot = serde_json::json!({
+            "created_at": 0,
+            "source": "test",
+            "manufacturers": manufacturers,
+        });
+        std::fs::write(&path, snapshot.to_string()).unwrap();
+        path.to_str().unwrap().to_string()
+    };
+    let old = snapshot(
+        "old.json",
+        serde_json::json!([
+            {"Mfr_ID": 1, "Mfr_Name": "KEPT", "Mfr_CommonName": null, "Country": "GERMANY"},
+            {"Mfr_ID": 2, "Mfr_Name": "REMOVED", "Mfr_CommonName": null, "Country": "FRANCE"},
+            {"Mfr_ID": 3, "Mfr_Name": "MOVED", "Mfr_CommonName": null, "Country": "JAPAN"},
+        ]),
+    );
+    let new = snapshot(
+        "new.json",
+        serde_json::json!([
+            {"Mfr_ID": 1, "Mfr_Name": "KEPT", "Mfr_CommonName": null, "Country": "GERMANY"},
+            {"Mfr_ID": 3, "Mfr_Name": "MOVED", "Mfr_CommonName": null, "Country": "CHINA"},
+            {"Mfr_ID": 4, "Mfr_Name": "ADDED", "Mfr_CommonName": null, "Country": null},
+        ]),
+    );
+
+    let output = run(&server, &home, &["diff", &old, &new]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Added (1):\n\t+ 4 ADDED (unknown country)\n\
+         Removed (1):\n\t- 2 REMOVED (FRANCE)\n\
+         Changed (1):\n\t~ 3 MOVED (CHINA)\n\t\tcountry: \"JAPAN\" -> \"CHINA\"\n"
+    );
+
+    let same = run(&server, &home, &["diff", &old, &old]).await;
+    assert_eq!(stdout(&same), "No differences\n");
+
+    let json = run(&server, &home, &["diff", &old, &new, "--output", "json"]).await;
+    assert!(json.status.success(), "{}", stderr(&json));
+    let entries: Value = serde_json::from_str(&stdout(&json)).unwrap();
+    let changes: Vec<(&str, u64)> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry["change"].as_str().unwrap(),
+                entry["id"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(changes, [("added", 4), ("removed", 2), ("changed", 3)]);
+    assert_eq!(
+        entries[2]["fields"],
+        serde_json::json!({"country": {"from": "JAPAN", "to": "CHINA"}})
+    );
+
+    let same = run(&server, &home, &["diff", &old, &old, "--output", "csv"]).await;
+    assert!(same.status.success(), "{}", stderr(&same));
+    assert_eq!(stdout(&same), "change,id,name,country,fields\n");
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn browses_makes_and_models() {
+    let server = paginated().await;
+    let home = temp_dir("browse");
+
+    let makes = run(
+        &server,
+        &home,
+        &["makes", "--manufacturer", "bmw", "--no-cache"],
+    )
+    .await;
+    assert!(stdout(&makes).contains("Name: MINI"));
+
+    let models = run(
+        &server,
+        &home,
+        &["models", "--make", "bmw", "--year", "2020", "--no-cache"],
+    )
+    .await;
+    assert!(stdout(&models).starts_with("Found 2 models: "));
+
+    let missing = run(&server, &home, &["models"]).await;
+    assert_eq!(missing.status.code(), Some(2));
+}
+
+#[tokio::test]
+async fn decodes_a_vin_end_to_end() {
+    let server = paginated().await;
+    let home = temp_dir("decode");
+    let output = run(
+        &server,
+        &home,
+        &["decode-vin", "1hgcm82633a004352", "--no-cache"],
+    )
+    .await;
+
+    let text = stdout(&output);
+    assert!(text.contains("WMI: 1HG (Honda, United States)"));
+    assert!(text.contains("Model: Accord"));
+
+    let json = run(
+        &server,
+        &home,
+        &["decode-vin", "1hgcm82633a004352", "--output", "json"],
+    )
+    .await;
+    assert!(json.status.success(), "{}", stderr(&json));
+    let decoded: Value = serde_json::from_str(&stdout(&json)).unwrap();
+    assert_eq!(decoded[0]["vin"], "1HGCM82633A004352");
+    assert_eq!(decoded[0]["wmi"], "1HG");
+    assert_eq!(decoded[0]["country"], "United States");
+    assert_eq!(decoded[0]["check_digit"], "3");
+    assert_eq!(decoded[0]["model"], "Accord");
+
+    let csv = run(
+        &server,
+        &home,
+        &[
+            "decode-vin",
+            "1hgcm82633a004352",
+            "--output",
+            "csv",
+            "--columns",
+            "vin,model",
+        ],
+    )
+    .await;
+    assert_eq!(stdout(&csv), "vin,model\n1HGCM82633A004352,Accord\n");
+
+    let invalid = run(&server, &home, &["decode-vin", "1HGCM82633A004353"]).await;
+    assert_eq!(invalid.status.code(), Some(8));
+}
+
+#[tokio::test]
+async fn vins_name_the_manufacturer_records_of_the_snapshot() {
+    let server = paginated().await;
+    let home = temp_dir("vin-records");
+    let vins = home.join("vins.csv");
+    std::fs::write(
+        &vins,
+        "vin,owner\nWBA3A5C57CF256987,fleet\n1HGCM82633A004352,fleet\n1HGCM82633A004353,fleet\n",
+    )
+    .unwrap();
+    let vins = vins.to_str().unwrap();
+
+    // Without a snapshot nothing is downloaded to find the records
+    let offline = run(&server, &home, &["validate-vins", vins]).await;
+    assert!(server.requests().is_empty());
+    let lines: Vec<String> = stdout(&offline).lines().map(str::to_string).collect();
+    assert_eq!(
+        lines[1],
+        "2\tWBA3A5C57CF256987\tvalid\tWBA\t3A5C57\tCF256987\t2012\tBMW\tGermany\t\t"
+    );
+
+    let sync = run(&server, &home, &["sync"]).await;
+    assert!(sync.status.success(), "{}", stderr(&sync));
+
+    let output = run(&server, &home, &["validate-vins", vins]).await;
+    assert_eq!(output.status.code(), Some(8));
+    let lines: Vec<String> = stdout(&output).lines().map(str::to_string).collect();
+    assert!(lines[0].ends_with("\terror\tmanufacturers"));
+    assert!(lines[1].ends_with(
+        "\tBMW\tGermany\t\tBAYERISCHE MOTOREN WERKE AG (ID 968); BMW OF NORTH AMERICA, LLC (ID 4108)"
+    ));
+    // Honda is in the WMI table but not in the snapshot
+    assert!(lines[2].ends_with("\tHonda\tUnited States\t\t"));
+    assert!(lines[3].contains("\tinvalid\t"));
+    assert!(stderr(&output).starts_with("2 valid, 1 invalid\n"));
+
+    let ndjson = run(
+        &server,
+        &home,
+        &[
+            "validate-vins",
+            vins,
+            "--output",
+            "ndjson",
+            "--columns",
+            "line,vin,status,make,error",
+        ],
+    )
+    .await;
+    assert_eq!(ndjson.status.code(), Some(8));
+    let checks: Vec<Value> = stdout(&ndjson)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(checks.len(), 3);
+    assert_eq!(
+        checks[0],
+        serde_json::json!({"line": 2, "vin": "WBA3A5C57CF256987", "status": "valid", "make": "BMW", "error": null})
+    );
+    assert_eq!(checks[2]["status"], "invalid");
+    assert_eq!(checks[2]["make"], Value::Null);
+    assert!(checks[2]["error"].as_str().unwrap().contains("check digit"));
+
+    let decoded = run(&server, &home, &["decode-vin", "WBA3A5C57CF256987"]).await;
+    assert!(stdout(&decoded).contains(
+        "\tManufacturer Records: BAYERISCHE MOTOREN WERKE AG (ID 968), BMW OF NORTH AMERICA, LLC (ID 4108)\n"
+    ));
+}
+
+#[tokio::test]
+async fn closed_pipes_end_the_output_quietly() {
+    let server = paginated().await;
+    let home = temp_dir("closed-pipe");
+    // Far more than a pipe holds, so writing has to fail
+    let vins = home.join("vins.txt");
+    std::fs::write(&vins, "WBA3A5C57CF256987\n".repeat(20_000)).unwrap();
+
+    let output =
+        run_into_closed_pipe(&server, &home, &["validate-vins", vins.to_str().unwrap()]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(!stderr(&output).contains("panicked"), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn aliases_fold_legal_names_into_one_result() {
+    let server = paginated().await;
+    let home = temp_dir("aliases");
+    let aliases = home.join("aliases");
+    std::fs::write(
+        &aliases,
+        "# BMW AG and its US arm\nBMW = BMW of North America\n",
+    )
+    .unwrap();
+    let aliases = aliases.to_str().unwrap();
+
+    let folded = run(
+        &server,
+        &home,
+        &[
+            "bmw",
+            "--no-cache",
+            "--aliases",
+            aliases,
+            "--output",
+            "csv",
+            "--columns",
+            "id",
+        ],
+    )
+    .await;
+    assert_eq!(stdout(&folded), "id\n968\n");
+
+    let all = run(
+        &server,
+        &home,
+        &[
+            "bmw",
+            "--no-cache",
+            "--aliases",
+            aliases,
+            "--all-names",
+            "--output",
+            "csv",
+            "--columns",
+            "id",
+        ],
+    )
+    .await;
+    assert_eq!(stdout(&all), "id\n968\n4108\n");
+}
+
+#[tokio::test]
+async fn enrich_adds_makes_and_contact_details() {
+    let server = MockServer::start(vec![
+        (
+            "getallmanufacturers?format=json&page=1$",
+            Reply::Fixture("manufacturers_page1.json"),
+        ),
+        ("getallmanufacturers", Reply::Fixture("empty.json")),
+        (
+            "GetManufacturerDetails/968",
+            Reply::Fixture("manufacturer_details_bmw.json"),
+        ),
+        (
+            "GetMakeForManufacturer/968",
+            Reply::Fixture("makes_bmw.json"),
+        ),
+    ])
+    .await;
+    let home = temp_dir("enrich");
+    let output = run(
+        &server,
+        &home,
+        &[
+            "bmw",
+            "--no-cache",
+            "--enrich",
+            "--output",
+            "csv",
+            "--columns",
+            "id,makes,city",
+        ],
+    )
+    .await;
+
+    assert_eq!(stdout(&output), "id,makes,city\n968,BMW; MINI,MUNICH\n");
+}
+
+#[tokio::test]
+async fn config_profiles_set_defaults_that_flags_override() {
+    let server = paginated().await;
+    let home = temp_dir("config");
+    let dir = home.join("config").join("manufacturers");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "[profiles.default]\noutput = \"csv\"\n\n[profiles.names]\nfields = [\"name\"]\n",
+    )
+    .unwrap();
+
+    let output = run(
+        &server,
+        &home,
+        &["germany", "--no-cache", "--columns", "id"],
+    )
+    .await;
+    assert_eq!(stdout(&output), "id\n968\n4108\n");
+
+    // Only the name is searched now, and no name mentions Germany
+    let names = run(
+        &server,
+        &home,
+        &["germany", "--no-cache", "--profile", "names"],
+    )
+    .await;
+    assert_eq!(names.status.code(), Some(1));
+
+    let text = run(&server, &home, &["tesla", "--no-cache", "--output", "text"]).await;
+    assert!(stdout(&text).starts_with("Found 1 manufacturers: "));
+
+    let unknown = run(&server, &home, &["bmw", "--profile", "staging"]).await;
+    assert_eq!(unknown.status.code(), Some(2));
+}
+
+#[tokio::test]
+async fn config_values_are_checked_like_their_flags() {
+    let server = paginated().await;
+    let home = temp_dir("config-invalid");
+    let dir = home.join("config").join("manufacturers");
+    std::fs::create_dir_all(&dir).unwrap();
+    for key in ["timeout", "connect_timeout", "cache_ttl", "concurrency"] {
+        std::fs::write(
+            dir.join("config.toml"),
+            format!("[profiles.default]\n{} = 0\n", key),
+        )
+        .unwrap();
+        let output = run(&server, &home, &["bmw", "--no-cache"]).await;
+        assert_eq!(output.status.code(), Some(8), "{}", key);
+        assert!(
+            stderr(&output).contains(&format!("Invalid config: {} must be at least 1", key)),
+            "{}",
+            stderr(&output)
+        );
+    }
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn compare_shows_two_manufacturers_side_by_side() {
+    let server = MockServer::start(vec![
+        (
+            "getallmanufacturers?format=json&page=1$",
+            Reply::Fixture("manufacturers_page1.json"),
+        ),
+        (
+            "getallmanufacturers?format=json&page=2$",
+            Reply::Fixture("manufacturers_page2.json"),
+        ),
+        ("getallmanufacturers", Reply::Fixture("empty.json")),
+        (
+            "GetManufacturerDetails/968",
+            Reply::Fixture("manufacturer_details_bmw.json"),
+        ),
+        ("GetManufacturerDetails/", Reply::Fixture("empty.json")),
+        (
+            "GetMakeForManufacturer/968",
+            Reply::Fixture("makes_bmw.json"),
+        ),
+        ("GetMakeForManufacturer/", Reply::Fixture("empty.json")),
+    ])
+    .await;
+    let home = temp_dir("compare");
+
+    let output = run(&server, &home, &["compare", "bmw", "955", "--no-cache"]).await;
+    let stdout = stdout(&output);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines[0].split_whitespace().collect::<Vec<_>>(),
+        ["BMW", "(ID", "968)", "Tesla", "(ID", "955)"]
+    );
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("* City") && line.contains("MUNICH") && line.ends_with("-")));
+    assert!(lines.contains(&"                  MINI"));
+    assert!(stdout.ends_with("8 of 8 fields differ (marked *)\n"));
+
+    let json = run(
+        &server,
+        &home,
+        &[
+            "compare",
+            "tesla",
+            "tesla",
+            "--no-cache",
+            "--output",
+            "json",
+        ],
+    )
+    .await;
+    let rows: Value = serde_json::from_str(&common::stdout(&json)).unwrap();
+    assert!(rows
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|row| row["same"] == true));
+
+    let missing = run(&server, &home, &["compare", "bmw", "zzzz", "--no-cache"]).await;
+    assert_eq!(missing.status.code(), Some(1));
+}
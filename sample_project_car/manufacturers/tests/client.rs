@@ -0,0 +1,293 @@
+// vPIC client parsing against recorded fixtures
+mod common;
+
+use common::{MockServer, Reply};
+use manufacturers::retry::RetryPolicy;
+use manufacturers::{Error, Vin, VpicClient};
+
+fn client(server: &MockServer) -> VpicClient {
+    VpicClient::with_base_url(&server.base_url).with_retry(RetryPolicy::none())
+}
+
+// Two pages of manufacturers followed by empty pages
+async fn paginated() -> MockServer {
+    MockServer::start(vec![
+        (
+            "getallmanufacturers?format=json&page=1$",
+            Reply::Fixture("manufacturers_page1.json"),
+        ),
+        (
+            "getallmanufacturers?format=json&page=2$",
+            Reply::Fixture("manufacturers_page2.json"),
+        ),
+        ("getallmanufacturers", Reply::Fixture("empty.json")),
+    ])
+    .await
+}
+
+#[tokio::test]
+async fn parses_a_page_of_manufacturers() {
+    let server = paginated().await;
+    let manufacturers = client(&server).all_manufacturers(1).await.unwrap();
+
+    assert_eq!(manufacturers.len(), 3);
+    let bmw = &manufacturers[0];
+    assert_eq!(bmw.id, 968);
+    assert_eq!(bmw.name.as_deref(), Some("BAYERISCHE MOTOREN WERKE AG"));
+    assert_eq!(bmw.common_name.as_deref(), Some("BMW"));
+    assert_eq!(bmw.vehicle_types.len(), 2);
+    assert!(bmw.vehicle_types[0].is_primary);
+    assert_eq!(manufacturers[2].common_name.as_deref(), Some("Citroën"));
+}
+
+#[tokio::test]
+async fn null_fields_become_none() {
+    let server = paginated().await;
+    let manufacturers = client(&server).all_manufacturers(2).await.unwrap();
+
+    let trailers = &manufacturers[1];
+    assert_eq!(trailers.country, None);
+    assert_eq!(trailers.common_name, None);
+}
+
+#[tokio::test]
+async fn pagination_stops_at_the_first_empty_page() {
+    let server = paginated().await;
+    let manufacturers = client(&server)
+        .all_manufacturers_paged(1, 50)
+        .await
+        .unwrap();
+
+    assert_eq!(manufacturers.len(), 5);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn concurrent_pages_keep_their_order() {
+    let server = paginated().await;
+    let manufacturers = client(&server)
+        .all_manufacturers_paged(4, 50)
+        .await
+        .unwrap();
+
+    let ids: Vec<u32> = manufacturers
+        .iter()
+        .map(|manufacturer| manufacturer.id)
+        .collect();
+    assert_eq!(ids, [968, 955, 1120, 4108, 7011]);
+}
+
+#[tokio::test]
+async fn failed_pages_past_the_end_are_ignored() {
+    let server = MockServer::start(vec![
+        (
+            "getallmanufacturers?format=json&page=1$",
+            Reply::Fixture("manufacturers_page1.json"),
+        ),
+        (
+            "getallmanufacturers?format=json&page=2$",
+            Reply::Fixture("manufacturers_page2.json"),
+        ),
+        (
+            "getallmanufacturers?format=json&page=3$",
+            Reply::Fixture("empty.json"),
+        ),
+        ("getallmanufacturers", Reply::Status(500, None)),
+    ])
+    .await;
+    let manufacturers = client(&server)
+        .all_manufacturers_paged(8, 50)
+        .await
+        .unwrap();
+
+    assert_eq!(manufacturers.len(), 5);
+}
+
+#[tokio::test]
+async fn failed_pages_before_the_end_are_errors() {
+    let server = MockServer::start(vec![
+        (
+            "getallmanufacturers?format=json&page=1$",
+            Reply::Fixture("manufacturers_page1.json"),
+        ),
+        (
+            "getallmanufacturers?format=json&page=2$",
+            Reply::Status(500, None),
+        ),
+        ("getallmanufacturers", Reply::Fixture("empty.json")),
+    ])
+    .await;
+    let error = client(&server)
+        .all_manufacturers_paged(8, 50)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, Error::Status { status, .. } if status.as_u16() == 500));
+}
+
+#[tokio::test]
+async fn huge_concurrency_does_not_overflow() {
+    let server = paginated().await;
+    let manufacturers = client(&server)
+        .all_manufacturers_paged(usize::MAX, 50)
+        .await
+        .unwrap();
+
+    assert_eq!(manufacturers.len(), 5);
+}
+
+#[tokio::test]
+async fn max_pages_limits_the_download() {
+    let server = paginated().await;
+    let manufacturers = client(&server).all_manufacturers_paged(1, 1).await.unwrap();
+
+    assert_eq!(manufacturers.len(), 3);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn truncated_json_is_a_decode_error() {
+    let server = MockServer::start(vec![("", Reply::Fixture("truncated.json"))]).await;
+    let error = client(&server).all_manufacturers(1).await.unwrap_err();
+
+    assert!(matches!(error, Error::Decode { .. }));
+    assert_eq!(error.exit_code(), 5);
+}
+
+#[tokio::test]
+async fn wrong_field_types_are_a_decode_error() {
+    let server = MockServer::start(vec![("", Reply::Fixture("wrong_shape.json"))]).await;
+    let error = client(&server).all_manufacturers(1).await.unwrap_err();
+
+    assert!(matches!(error, Error::Decode { .. }));
+}
+
+#[tokio::test]
+async fn unknown_routes_are_status_errors() {
+    let server = MockServer::start(Vec::new()).await;
+    let error = client(&server).all_manufacturers(1).await.unwrap_err();
+
+    assert!(matches!(error, Error::Status { status, .. } if status.as_u16() == 404));
+    assert!(error.to_string().starts_with(&server.base_url), "{}", error);
+    assert!(
+        error.to_string().ends_with("answered 404 Not Found"),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn unreachable_servers_are_named_in_the_error() {
+    // A port that was just free, nothing listens on it any more
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    drop(listener);
+    let error = VpicClient::with_base_url(format!("http://{}", address))
+        .with_retry(RetryPolicy::none())
+        .all_manufacturers(1)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, Error::Network(_)), "{}", error);
+    assert!(
+        error.to_string().starts_with("Could not reach 127.0.0.1: "),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn parses_makes_and_models() {
+    let server = MockServer::start(vec![
+        (
+            "GetMakeForManufacturer/bmw",
+            Reply::Fixture("makes_bmw.json"),
+        ),
+        (
+            "GetModelsForMakeYear/make/bmw/modelyear/2020",
+            Reply::Fixture("models_bmw_2020.json"),
+        ),
+    ])
+    .await;
+    let client = client(&server);
+
+    let makes = client.makes_for_manufacturer("bmw").await.unwrap();
+    assert_eq!(makes.len(), 2);
+    assert_eq!(makes[1].name.as_deref(), Some("MINI"));
+
+    let models = client.models_for_make("bmw", Some(2020)).await.unwrap();
+    assert_eq!(models[0].name.as_deref(), Some("X5"));
+    assert_eq!(models[0].make_id, 452);
+}
+
+#[tokio::test]
+async fn manufacturer_names_are_percent_encoded() {
+    let server = MockServer::start(vec![("", Reply::Fixture("makes_bmw.json"))]).await;
+    client(&server)
+        .makes_for_manufacturer("mercedes benz")
+        .await
+        .unwrap();
+
+    assert!(server.requests()[0].starts_with("/GetMakeForManufacturer/mercedes%20benz?"));
+}
+
+#[tokio::test]
+async fn decodes_a_vin() {
+    let server = MockServer::start(vec![(
+        "DecodeVinValues/1HGCM82633A004352",
+        Reply::Fixture("decode_vin.json"),
+    )])
+    .await;
+    let vin = Vin::parse("1HGCM82633A004352").unwrap();
+
+    let decoded = client(&server).decode_vin(&vin).await.unwrap().unwrap();
+    assert_eq!(decoded.make, "HONDA");
+    assert_eq!(decoded.model_year, "2003");
+    assert!(!decoded.has_errors());
+}
+
+#[tokio::test]
+async fn filtered_download_keeps_only_matches() {
+    let server = paginated().await;
+    let manufacturers = client(&server)
+        .all_manufacturers_filtered(1, 50, |manufacturer| {
+            manufacturer.country.as_deref() == Some("FRANCE")
+        })
+        .await
+        .unwrap();
+
+    let ids: Vec<u32> = manufacturers
+        .iter()
+        .map(|manufacturer| manufacturer.id)
+        .collect();
+    assert_eq!(ids, [1120]);
+    // Pages without a match do not end the download, only empty ones do
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn enrichment_looks_up_every_match_by_id() {
+    let server = MockServer::start(vec![
+        (
+            "GetManufacturerDetails/968",
+            Reply::Fixture("manufacturer_details_bmw.json"),
+        ),
+        ("GetManufacturerDetails/", Reply::Fixture("empty.json")),
+        ("GetMakeForManufacturer/", Reply::Fixture("makes_bmw.json")),
+    ])
+    .await;
+    let paged = paginated().await;
+    let manufacturers = client(&paged).all_manufacturers(1).await.unwrap();
+    // More permits than a semaphore can hold are capped
+    let enriched = manufacturers::enrich::enrich(&client(&server), manufacturers, usize::MAX)
+        .await
+        .unwrap();
+
+    let ids: Vec<u32> = enriched.iter().map(|e| e.manufacturer.id).collect();
+    assert_eq!(ids, [968, 955, 1120]);
+    let details = enriched[0].details.as_ref().unwrap();
+    assert_eq!(details.city.as_deref(), Some("MUNICH"));
+    assert!(enriched[1].details.is_none());
+    assert_eq!(enriched[2].makes.len(), 2);
+    assert_eq!(server.requests().len(), 6);
+}
@@ -0,0 +1,245 @@
+// Local stand-in for the vPIC API serving recorded fixtures, shared by the integration tests
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::process::Command;
+
+// What the mock answers to a request
+#[derive(Debug, Clone)]
+pub enum Reply {
+    // A file from tests/fixtures, served with status 200
+    Fixture(&'static str),
+    // An empty body with the status and an optional Retry-After
+    Status(u16, Option<&'static str>),
+    // Like Fixture, with the ETag and Last-Modified validators
+    Tagged(&'static str, &'static str, &'static str),
+    // Accept the request and never answer
+    Hang(Duration),
+}
+
+pub fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("fixture {}: {}", path.display(), e))
+}
+
+// A pattern matches when it is part of the target, or its end when it ends with $
+// so "page=1$" does not catch page=10
+fn matches(target: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('$') {
+        Some(end) => target.ends_with(end),
+        None => target.contains(pattern),
+    }
+}
+
+pub struct MockServer {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+    heads: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    // Answer with the first route matching the request target, 404 otherwise
+    pub async fn start(routes: Vec<(&'static str, Reply)>) -> MockServer {
+        MockServer::scripted(Vec::new(), routes).await
+    }
+
+    // Serve the script one reply per request first, then fall back to the routes
+    pub async fn scripted(script: Vec<Reply>, routes: Vec<(&'static str, Reply)>) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let script = Arc::new(Mutex::new(VecDeque::from(script)));
+        let routes = Arc::new(routes);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let heads = Arc::new(Mutex::new(Vec::new()));
+
+        let seen = requests.clone();
+        let seen_heads = heads.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let script = script.clone();
+                let routes = routes.clone();
+                let seen = seen.clone();
+                let seen_heads = seen_heads.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    while !request.ends_with(b"\r\n\r\n") {
+                        match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let target = request
+                        .split_whitespace()
+                        .nth(1)
+                        .unwrap_or_default()
+                        .to_string();
+                    seen.lock().unwrap().push(target.clone());
+                    seen_heads.lock().unwrap().push(request.to_string());
+
+                    let scripted = script.lock().unwrap().pop_front();
+                    let reply = scripted.or_else(|| {
+                        routes
+                            .iter()
+                            .find(|(pattern, _)| matches(&target, pattern))
+                            .map(|(_, reply)| reply.clone())
+                    });
+                    let response = match reply {
+                        Some(Reply::Fixture(name)) => {
+                            let body = fixture(name);
+                            format!(
+                                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                                body.len(),
+                                body
+                            )
+                        }
+                        Some(Reply::Tagged(name, etag, last_modified)) => {
+                            let body = fixture(name);
+                            format!(
+                                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: {}\r\nLast-Modified: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                                etag,
+                                last_modified,
+                                body.len(),
+                                body
+                            )
+                        }
+                        Some(Reply::Status(code, retry_after)) => format!(
+                            "HTTP/1.1 {} Failure\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+                            code,
+                            retry_after
+                                .map(|value| format!("Retry-After: {}\r\n", value))
+                                .unwrap_or_default()
+                        ),
+                        Some(Reply::Hang(duration)) => {
+                            tokio::time::sleep(duration).await;
+                            return;
+                        }
+                        None => {
+                            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                                .to_string()
+                        }
+                    };
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        MockServer {
+            base_url,
+            requests,
+            heads,
+        }
+    }
+
+    // Request targets seen so far, e.g. "/getallmanufacturers?format=json&page=1"
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    // Request lines and headers as received, in the same order as requests()
+    pub fn heads(&self) -> Vec<String> {
+        self.heads.lock().unwrap().clone()
+    }
+}
+
+// An empty directory for one test
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "manufacturers-test-{}-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Run the binary against the mock, with cache, snapshot and config kept in `home`
+fn command(server: &MockServer, home: &Path, args: &[&str]) -> Command {
+    let mut command = configured(home, args);
+    command.args(["--base-url", &server.base_url]);
+    command
+}
+
+// The binary with its files below `home`, the base URL comes from the config
+fn configured(home: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_manufacturers"));
+    command
+        .args(args)
+        .args(["--retries", "0"])
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CONFIG_HOME", home.join("config"));
+    command
+}
+
+pub async fn run(server: &MockServer, home: &Path, args: &[&str]) -> Output {
+    command(server, home, args).output().await.unwrap()
+}
+
+// Same as run, without --base-url so the one of the config or the default is used
+pub async fn run_without_base_url(home: &Path, args: &[&str]) -> Output {
+    configured(home, args).output().await.unwrap()
+}
+
+// Same as run, without XDG variables so everything goes below $HOME
+pub async fn run_without_xdg(server: &MockServer, home: &Path, args: &[&str]) -> Output {
+    command(server, home, args)
+        .env_remove("XDG_CACHE_HOME")
+        .env_remove("XDG_DATA_HOME")
+        .env_remove("XDG_CONFIG_HOME")
+        .env("HOME", home)
+        .output()
+        .await
+        .unwrap()
+}
+
+// Same as run, with the given text on standard input
+pub async fn run_with_input(
+    server: &MockServer,
+    home: &Path,
+    args: &[&str],
+    input: &str,
+) -> Output {
+    let mut child = command(server, home, args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(input.as_bytes()).await.unwrap();
+    drop(stdin);
+    child.wait_with_output().await.unwrap()
+}
+
+// Same as run, with standard output closed before anything is read, as with `| head -0`
+pub async fn run_into_closed_pipe(server: &MockServer, home: &Path, args: &[&str]) -> Output {
+    let mut child = command(server, home, args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    child.wait_with_output().await.unwrap()
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
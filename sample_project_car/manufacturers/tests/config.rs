@@ -0,0 +1,45 @@
+// Config file parsing and profile selection
+use manufacturers::config::Config;
+use manufacturers::Error;
+
+const CONFIG: &str = r#"
+profile = "mirror"
+
+[profiles.default]
+timeout = 60
+output = "table"
+
+[profiles.mirror]
+base_url = "http://localhost:8080/api/vehicles"
+output = "json"
+
+[profiles.production]
+"#;
+
+#[test]
+fn selected_profile_applies_on_top_of_the_default() {
+    let config = Config::parse(CONFIG).unwrap();
+
+    let mirror = config.settings(None).unwrap();
+    assert_eq!(
+        mirror.base_url.as_deref(),
+        Some("http://localhost:8080/api/vehicles")
+    );
+    assert_eq!(mirror.output.as_deref(), Some("json"));
+    assert_eq!(mirror.timeout, Some(60));
+
+    let production = config.settings(Some("production")).unwrap();
+    assert_eq!(production.base_url, None);
+    assert_eq!(production.output.as_deref(), Some("table"));
+}
+
+#[test]
+fn unknown_profiles_and_keys_are_errors() {
+    let config = Config::parse(CONFIG).unwrap();
+    let error = config.settings(Some("staging")).unwrap_err();
+    assert!(matches!(error, Error::Usage(_)));
+    assert!(error.to_string().contains("default, mirror, production"));
+
+    assert!(Config::parse("[profiles.default]\ntimout = 3\n").is_err());
+    assert!(Config::default().settings(None).is_ok());
+}
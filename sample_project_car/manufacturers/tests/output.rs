@@ -0,0 +1,99 @@
+// Structured output: CSV quoting, table alignment and JSON
+use manufacturers::models::VehicleType;
+use manufacturers::output::{self, Format};
+use manufacturers::Manufacturer;
+use serde_json::Value;
+
+fn manufacturer(id: u32, name: &str, country: &str) -> Manufacturer {
+    Manufacturer {
+        id,
+        name: Some(name.to_string()),
+        common_name: None,
+        country: Some(country.to_string()),
+        vehicle_types: Vec::new(),
+    }
+}
+
+#[test]
+fn csv_quotes_separators_quotes_and_line_breaks() {
+    let mut quoted = manufacturer(1, "ACME, \"BEST\" TRAILERS\nINC", "FRANCE");
+    quoted.vehicle_types = ["Trailer", "Truck"]
+        .iter()
+        .map(|name| VehicleType {
+            is_primary: false,
+            name: Some(name.to_string()),
+        })
+        .collect();
+    let plain = manufacturer(2, "TESLA", "UNITED STATES (USA)");
+
+    assert_eq!(
+        output::render(
+            Format::Csv,
+            &[quoted, plain],
+            &["id", "name", "country", "vehicle_types"]
+        ),
+        "id,name,country,vehicle_types\n\
+         1,\"ACME, \"\"BEST\"\" TRAILERS\nINC\",FRANCE,Trailer; Truck\n\
+         2,TESLA,UNITED STATES (USA),\n"
+    );
+    // The header alone when nothing matched
+    assert_eq!(
+        output::render::<Manufacturer>(Format::Csv, &[], &["id"]),
+        "id\n"
+    );
+}
+
+#[test]
+fn table_columns_line_up_with_non_ascii_names() {
+    let manufacturers = [
+        manufacturer(955, "AUTOMOBILES CITROËN", "FRANCE"),
+        manufacturer(968, "BMW", "GERMANY"),
+        manufacturer(7, "ŠKODA AUTO A.S.", "CZECHIA"),
+    ];
+    let table = output::render(Format::Table, &manufacturers, &["id", "name", "country"]);
+
+    assert_eq!(
+        table,
+        "id   name                 country\n\
+         ---  -------------------  -------\n\
+         955  AUTOMOBILES CITROËN  FRANCE\n\
+         968  BMW                  GERMANY\n\
+         7    ŠKODA AUTO A.S.      CZECHIA\n"
+    );
+    // The country column starts at the same character on every line, not byte
+    for line in table.lines() {
+        let chars: Vec<char> = line.chars().collect();
+        assert_eq!(chars[24..26], [' ', ' '], "{}", line);
+        assert_ne!(chars[26], ' ', "{}", line);
+    }
+}
+
+#[test]
+fn json_is_valid_whatever_the_names() {
+    let manufacturers = [manufacturer(1, "ACME \"BEST\"\nTRAILERS", "CÔTE D'IVOIRE")];
+    let json = output::render(Format::Json, &manufacturers, &["id", "name", "country"]);
+    let parsed: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed[0]["name"], "ACME \"BEST\"\nTRAILERS");
+    assert_eq!(parsed[0]["country"], "CÔTE D'IVOIRE");
+
+    let ndjson = output::render(Format::Ndjson, &manufacturers, &["name"]);
+    assert_eq!(ndjson.lines().count(), 1);
+    assert_eq!(
+        output::render::<Manufacturer>(Format::Json, &[], &["id"]),
+        "[]\n"
+    );
+}
+
+#[test]
+fn columns_are_picked_and_checked() {
+    assert_eq!(
+        output::select_columns::<Manufacturer>(Some("name, id,")),
+        Ok(vec!["name", "id"])
+    );
+    assert_eq!(
+        output::select_columns::<Manufacturer>(None).unwrap().len(),
+        6
+    );
+    let error = output::select_columns::<Manufacturer>(Some("id,make")).unwrap_err();
+    assert!(error.starts_with("Unknown column make"), "{}", error);
+}
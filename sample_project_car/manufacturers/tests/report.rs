@@ -0,0 +1,117 @@
+// Counting manufacturers per country or vehicle type
+use manufacturers::models::VehicleType;
+use manufacturers::report::{self, Group, GroupBy, UNKNOWN};
+use manufacturers::Manufacturer;
+
+fn manufacturer(country: Option<&str>, vehicle_types: &[&str]) -> Manufacturer {
+    Manufacturer {
+        id: 1,
+        name: None,
+        common_name: None,
+        country: country.map(str::to_string),
+        vehicle_types: vehicle_types
+            .iter()
+            .map(|name| VehicleType {
+                is_primary: false,
+                name: Some(name.to_string()),
+            })
+            .collect(),
+    }
+}
+
+fn group(key: &str, count: usize, percent: f64) -> Group {
+    Group {
+        key: key.to_string(),
+        count,
+        percent,
+    }
+}
+
+#[test]
+fn countries_are_counted_with_their_share() {
+    let manufacturers = [
+        manufacturer(Some("GERMANY"), &[]),
+        manufacturer(Some("JAPAN"), &[]),
+        manufacturer(Some(" GERMANY "), &[]),
+        manufacturer(None, &[]),
+        manufacturer(Some(""), &[]),
+        manufacturer(Some("FRANCE"), &[]),
+        manufacturer(Some("GERMANY"), &[]),
+        manufacturer(Some("JAPAN"), &[]),
+    ];
+
+    // Largest first, ties by name, missing and blank values are unknown
+    assert_eq!(
+        report::group(&manufacturers, GroupBy::Country),
+        [
+            group("GERMANY", 3, 37.5),
+            group(UNKNOWN, 2, 25.0),
+            group("JAPAN", 2, 25.0),
+            group("FRANCE", 1, 12.5),
+        ]
+    );
+}
+
+#[test]
+fn a_manufacturer_counts_once_per_vehicle_type() {
+    let manufacturers = [
+        // Listed twice, still one manufacturer building trucks
+        manufacturer(None, &["Truck", "Bus", "Truck"]),
+        manufacturer(None, &["Truck"]),
+        manufacturer(None, &[]),
+        manufacturer(None, &["Passenger Car"]),
+    ];
+
+    // Each type is a share of all manufacturers, together they exceed 100
+    assert_eq!(
+        report::group(&manufacturers, GroupBy::VehicleType),
+        [
+            group("Truck", 2, 50.0),
+            group(UNKNOWN, 1, 25.0),
+            group("Bus", 1, 25.0),
+            group("Passenger Car", 1, 25.0),
+        ]
+    );
+}
+
+#[test]
+fn no_manufacturers_give_no_groups() {
+    assert!(report::group(&[], GroupBy::Country).is_empty());
+}
+
+#[test]
+fn top_folds_the_rest_into_others() {
+    let groups = vec![
+        group("GERMANY", 4, 40.0),
+        group("JAPAN", 3, 30.0),
+        group("FRANCE", 2, 20.0),
+        group("ITALY", 1, 10.0),
+    ];
+
+    assert_eq!(
+        report::top(groups.clone(), 2),
+        [
+            group("GERMANY", 4, 40.0),
+            group("JAPAN", 3, 30.0),
+            group("(2 others)", 3, 30.0),
+        ]
+    );
+    assert_eq!(
+        report::top(groups.clone(), 3)[3],
+        group("(1 other)", 1, 10.0)
+    );
+    assert_eq!(
+        report::top(groups.clone(), 0),
+        [group("(4 others)", 10, 100.0)]
+    );
+    // Nothing to fold
+    assert_eq!(report::top(groups.clone(), 4), groups);
+    assert_eq!(report::top(groups.clone(), 10), groups);
+}
+
+#[test]
+fn groups_are_parsed_from_the_flag_value() {
+    assert_eq!("country".parse(), Ok(GroupBy::Country));
+    assert_eq!("vehicle-type".parse(), Ok(GroupBy::VehicleType));
+    assert!("make".parse::<GroupBy>().is_err());
+}
@@ -0,0 +1,130 @@
+// Retries and timeouts against a local server that fails on purpose
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{MockServer, Reply};
+use manufacturers::retry::{self, RetryPolicy};
+use manufacturers::{Error, VpicClient};
+
+// Every scripted failure is followed by the first page of manufacturers
+async fn mock(script: Vec<Reply>) -> MockServer {
+    MockServer::scripted(
+        script,
+        vec![("", Reply::Fixture("manufacturers_page1.json"))],
+    )
+    .await
+}
+
+fn quick_retries(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_retries,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_secs(2),
+    }
+}
+
+#[tokio::test]
+async fn retries_server_errors_until_success() {
+    let server = mock(vec![Reply::Status(500, None), Reply::Status(503, None)]).await;
+    let client = VpicClient::with_base_url(&server.base_url).with_retry(quick_retries(3));
+
+    let manufacturers = client.all_manufacturers(1).await.unwrap();
+    assert_eq!(manufacturers.len(), 3);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_retries() {
+    let server = mock(vec![Reply::Status(502, None); 5]).await;
+    let client = VpicClient::with_base_url(&server.base_url).with_retry(quick_retries(2));
+
+    let error = client.all_manufacturers(1).await.unwrap_err();
+    assert!(matches!(error, Error::Status { status, .. } if status.as_u16() == 502));
+    assert_eq!(error.exit_code(), 4);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let server = mock(vec![Reply::Status(404, None)]).await;
+    let client = VpicClient::with_base_url(&server.base_url).with_retry(quick_retries(3));
+
+    assert!(client.all_manufacturers(1).await.is_err());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn honors_retry_after_on_rate_limit() {
+    let server = mock(vec![Reply::Status(429, Some("1"))]).await;
+    let client = VpicClient::with_base_url(&server.base_url).with_retry(quick_retries(1));
+
+    let started = Instant::now();
+    client.all_manufacturers(1).await.unwrap();
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn retry_after_beyond_max_delay_gives_up() {
+    let server = mock(vec![Reply::Status(429, Some("3600"))]).await;
+    let client = VpicClient::with_base_url(&server.base_url).with_retry(quick_retries(3));
+
+    let error = client.all_manufacturers(1).await.unwrap_err();
+    assert!(matches!(error, Error::Status { status, .. } if status.as_u16() == 429));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn timed_out_requests_are_retried() {
+    let server = mock(vec![Reply::Hang(Duration::from_secs(5))]).await;
+    let client = VpicClient::with_base_url(&server.base_url)
+        .with_timeouts(Duration::from_secs(1), Duration::from_millis(200))
+        .unwrap()
+        .with_retry(quick_retries(1));
+
+    client.all_manufacturers(1).await.unwrap();
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn timeout_without_retries_is_a_network_error() {
+    let server = mock(vec![Reply::Hang(Duration::from_secs(5))]).await;
+    let client = VpicClient::with_base_url(&server.base_url)
+        .with_timeouts(Duration::from_secs(1), Duration::from_millis(200))
+        .unwrap()
+        .with_retry(RetryPolicy::none());
+
+    let error = client.all_manufacturers(1).await.unwrap_err();
+    assert!(matches!(&error, Error::Network(e) if e.is_timeout()));
+    assert_eq!(error.exit_code(), 3);
+}
+
+#[test]
+fn backoff_grows_and_stays_within_bounds() {
+    let policy = RetryPolicy {
+        max_retries: 10,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(1),
+    };
+    for attempt in 0..10 {
+        let full = Duration::from_millis(100 * 2u64.pow(attempt)).min(Duration::from_secs(1));
+        let delay = policy.delay(attempt, None).unwrap();
+        assert!(delay >= full / 2 && delay <= full, "attempt {}", attempt);
+    }
+    assert_eq!(policy.delay(10, None), None);
+}
+
+#[test]
+fn parses_retry_after_values() {
+    assert_eq!(
+        retry::parse_retry_after("120"),
+        Some(Duration::from_secs(120))
+    );
+    assert_eq!(
+        retry::parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT"),
+        Some(Duration::ZERO)
+    );
+    assert!(retry::parse_retry_after("Fri, 01 Jan 2100 00:00:00 GMT").unwrap() > Duration::ZERO);
+    assert_eq!(retry::parse_retry_after("soon"), None);
+}
@@ -0,0 +1,168 @@
+// Recalls and complaints through both data sources
+mod common;
+
+use std::path::Path;
+
+use common::{run, stdout, temp_dir, MockServer, Reply};
+use manufacturers::retry::RetryPolicy;
+use manufacturers::safety::{FixtureSource, NhtsaSource, Recall, Vehicle, VehicleDataSource};
+use manufacturers::{Error, VpicClient};
+
+fn accord() -> Vehicle {
+    Vehicle {
+        make: "Honda".to_string(),
+        model: "Accord".to_string(),
+        year: 2003,
+    }
+}
+
+fn fixtures() -> FixtureSource {
+    FixtureSource::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"))
+}
+
+#[tokio::test]
+async fn fixture_source_reads_saved_responses() {
+    let recalls = fixtures().recalls(&accord()).await.unwrap();
+    assert_eq!(recalls.len(), 2);
+    assert_eq!(recalls[0].campaign, "15V320000");
+    assert!(recalls[1].park_it);
+
+    let complaints = fixtures().complaints(&accord()).await.unwrap();
+    assert_eq!(complaints[0].odi_number, 10578946);
+    assert!(complaints[0].crash);
+    assert_eq!(complaints[0].number_of_injuries, 1);
+}
+
+fn recall(campaign: &str, report_date: Option<&str>) -> Recall {
+    Recall {
+        campaign: campaign.to_string(),
+        report_date: report_date.map(str::to_string),
+        component: None,
+        summary: None,
+        consequence: None,
+        remedy: None,
+        park_it: false,
+    }
+}
+
+#[test]
+fn recalls_sort_newest_first_across_centuries() {
+    let mut recalls = [
+        recall("99V123000", Some("15/06/1999")),
+        recall("03V456000", Some("02/01/2003")),
+        recall("03V111000", Some("20/11/2003")),
+        recall("98V001000", None),
+        recall("15V320000", None),
+        recall("03V222000", Some("20/11/2003")),
+        recall("??V000000", None),
+    ];
+    recalls.sort_by(|a, b| b.sort_key().cmp(&a.sort_key()));
+
+    let campaigns: Vec<&str> = recalls
+        .iter()
+        .map(|recall| recall.campaign.as_str())
+        .collect();
+    assert_eq!(
+        campaigns,
+        [
+            // Without a date the campaign year counts
+            "15V320000",
+            // Same day, the higher number first
+            "03V222000",
+            "03V111000",
+            "03V456000",
+            "99V123000",
+            "98V001000",
+            // Nothing to go by
+            "??V000000",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn fixture_source_reports_missing_vehicles() {
+    let civic = Vehicle {
+        model: "Civic".to_string(),
+        ..accord()
+    };
+    let error = fixtures().recalls(&civic).await.unwrap_err();
+    assert!(matches!(error, Error::Io { .. }));
+}
+
+#[tokio::test]
+async fn nhtsa_source_queries_by_vehicle() {
+    let server = MockServer::start(vec![
+        (
+            "/recalls/recallsByVehicle",
+            Reply::Fixture("recalls-honda-accord-2003.json"),
+        ),
+        (
+            "/complaints/complaintsByVehicle",
+            Reply::Fixture("complaints-honda-accord-2003.json"),
+        ),
+    ])
+    .await;
+    let source = NhtsaSource::new(
+        VpicClient::with_base_url(&server.base_url).with_retry(RetryPolicy::none()),
+    );
+
+    assert_eq!(source.recalls(&accord()).await.unwrap().len(), 2);
+    assert_eq!(source.complaints(&accord()).await.unwrap().len(), 1);
+    assert_eq!(
+        server.requests(),
+        [
+            "/recalls/recallsByVehicle?make=Honda&model=Accord&modelYear=2003",
+            "/complaints/complaintsByVehicle?make=Honda&model=Accord&modelYear=2003"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn nhtsa_failures_name_the_nhtsa_url() {
+    let server = MockServer::start(Vec::new()).await;
+    let source = NhtsaSource::new(
+        VpicClient::with_base_url(&server.base_url).with_retry(RetryPolicy::none()),
+    );
+    let error = source.recalls(&accord()).await.unwrap_err();
+
+    let message = error.to_string();
+    assert!(message.contains("/recalls/recallsByVehicle"), "{}", message);
+    assert!(!message.contains("vPIC"), "{}", message);
+}
+
+#[tokio::test]
+async fn recalls_command_reads_a_data_dir() {
+    let server = MockServer::start(Vec::new()).await;
+    let home = temp_dir("recalls");
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let output = run(
+        &server,
+        &home,
+        &[
+            "recalls",
+            "--make",
+            "honda",
+            "--model",
+            "accord",
+            "--year",
+            "2003",
+            "--data-dir",
+            dir.to_str().unwrap(),
+            "--output",
+            "csv",
+            "--columns",
+            "campaign,park_it",
+        ],
+    )
+    .await;
+
+    // Newest campaign first
+    assert_eq!(
+        stdout(&output),
+        "campaign,park_it\n19V182000,true\n15V320000,false\n"
+    );
+    assert!(server.requests().is_empty());
+
+    let missing = run(&server, &home, &["recalls", "--make", "honda"]).await;
+    assert_eq!(missing.status.code(), Some(2));
+}
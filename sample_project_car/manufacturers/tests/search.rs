@@ -0,0 +1,182 @@
+// Query parsing, scoring and the word index, against records built in the tests
+use manufacturers::models::VehicleType;
+use manufacturers::search::{Field, Index, Query};
+use manufacturers::Manufacturer;
+use proptest::prelude::*;
+
+fn manufacturer(id: u32, name: &str, common_name: Option<&str>, country: &str) -> Manufacturer {
+    Manufacturer {
+        id,
+        name: Some(name.to_string()),
+        common_name: common_name.map(str::to_string),
+        country: Some(country.to_string()),
+        vehicle_types: Vec::new(),
+    }
+}
+
+fn manufacturers() -> Vec<Manufacturer> {
+    vec![
+        manufacturer(968, "BAYERISCHE MOTOREN WERKE AG", Some("BMW"), "GERMANY"),
+        manufacturer(
+            976,
+            "FORD MOTOR COMPANY",
+            Some("Ford"),
+            "UNITED STATES (USA)",
+        ),
+        manufacturer(1, "FORD", None, "UNITED KINGDOM (UK)"),
+        manufacturer(2, "FORDSON TRACTORS", None, "IRELAND"),
+        manufacturer(3, "MUMFORD TRAILERS", None, "UNITED KINGDOM (UK)"),
+        manufacturer(4, "VOLKSWAGEN AG", Some("Volkswagen"), "GERMANY"),
+        manufacturer(5, "TOYOTA MOTOR CORPORATION", Some("Toyota"), "JAPAN"),
+        manufacturer(6, "MOTOR COACH INDUSTRIES", None, "CANADA"),
+    ]
+}
+
+fn ids(query: &str, manufacturers: &[Manufacturer]) -> Vec<u32> {
+    Query::parse(query)
+        .rank(manufacturers)
+        .into_iter()
+        .map(|(manufacturer, _)| manufacturer.id)
+        .collect()
+}
+
+#[test]
+fn typos_are_tolerated_in_longer_terms() {
+    let manufacturers = manufacturers();
+    // One typo in four to six chars, two from seven on
+    assert_eq!(ids("toyta", &manufacturers), [5]);
+    assert_eq!(ids("volksvagn", &manufacturers), [4]);
+    // Up to three chars have to match exactly
+    assert!(ids("bwm", &manufacturers).is_empty());
+    // Three typos are too many even for a long term
+    assert!(ids("vilksvagn", &manufacturers).is_empty());
+}
+
+#[test]
+fn prefixes_scope_terms_to_a_field() {
+    let manufacturers = manufacturers();
+    assert_eq!(ids("country:germany name:motor", &manufacturers), [968]);
+    assert_eq!(ids("name:motor", &manufacturers), [976, 6, 5, 968]);
+    // Germany is a country, not a name
+    assert!(ids("name:germany", &manufacturers).is_empty());
+    // Every term has to match
+    assert!(ids("country:japan name:ford", &manufacturers).is_empty());
+    // Quotes keep words together
+    assert_eq!(ids("name:\"motoren werke\"", &manufacturers), [968]);
+}
+
+#[test]
+fn default_fields_can_be_changed() {
+    let mut manufacturers = manufacturers();
+    manufacturers[3].vehicle_types = vec![VehicleType {
+        is_primary: true,
+        name: Some("Low Speed Vehicle (LSV)".to_string()),
+    }];
+    let query = Query::parse("speed");
+    assert!(query.rank(&manufacturers).is_empty());
+    let query = query.with_fields(&[Field::VehicleType]);
+    let found: Vec<u32> = query
+        .rank(&manufacturers)
+        .into_iter()
+        .map(|(manufacturer, _)| manufacturer.id)
+        .collect();
+    assert_eq!(found, [2]);
+}
+
+#[test]
+fn better_matches_rank_first() {
+    let manufacturers = manufacturers();
+    // The whole value, a whole word, a word prefix, then inside a word
+    let ranked = Query::parse("ford").rank(&manufacturers);
+    let order: Vec<(u32, f64)> = ranked
+        .iter()
+        .map(|(manufacturer, score)| (manufacturer.id, *score))
+        .collect();
+    assert_eq!(order, [(1, 1.0), (976, 1.0), (2, 0.9), (3, 0.8)]);
+
+    // Ties are sorted by name
+    assert_eq!(ids("motor", &manufacturers), [976, 6, 5, 968]);
+}
+
+#[test]
+fn empty_queries_match_everything() {
+    let manufacturers = manufacturers();
+    assert!(Query::parse("  ").terms.is_empty());
+    assert!(Query::parse("name:").terms.is_empty());
+    assert_eq!(ids("", &manufacturers).len(), manufacturers.len());
+}
+
+// Words the generated names and queries are made of, some sharing prefixes
+const WORDS: &[&str] = &[
+    "ford",
+    "fordson",
+    "motor",
+    "motoren",
+    "werke",
+    "bayerische",
+    "gmbh",
+    "ag",
+    "citroën",
+    "citroen",
+    "volkswagen",
+    "germany",
+    "japan",
+    "toyota",
+    "mumford",
+    "bmw",
+    "co",
+];
+
+fn value() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(WORDS), 1..4).prop_map(|words| words.join(" "))
+}
+
+fn record() -> impl Strategy<Value = (String, Option<String>, String)> {
+    (value(), prop::option::of(value()), value())
+}
+
+// A term as users type it: a known word, possibly cut short or with a typo,
+// possibly scoped to a field
+fn term() -> impl Strategy<Value = String> {
+    let prefix = prop::sample::select(vec!["", "name:", "common:", "country:"]);
+    let word = prop::sample::select(WORDS);
+    (prefix, word, 0usize..4, any::<bool>()).prop_map(|(prefix, word, cut, typo)| {
+        let mut text: String = word
+            .chars()
+            .take(word.chars().count().max(cut + 1) - cut)
+            .collect();
+        if typo {
+            text.push('x');
+        }
+        format!("{}{}", prefix, text)
+    })
+}
+
+proptest! {
+    #[test]
+    fn index_ranks_like_a_full_scan(
+        records in prop::collection::vec(record(), 0..30),
+        terms in prop::collection::vec(term(), 1..4),
+    ) {
+        let manufacturers: Vec<Manufacturer> = records
+            .iter()
+            .enumerate()
+            .map(|(id, (name, common_name, country))| {
+                manufacturer(id as u32, name, common_name.as_deref(), country)
+            })
+            .collect();
+        let query = Query::parse(&terms.join(" "));
+
+        let scanned: Vec<(u32, f64)> = query
+            .rank(&manufacturers)
+            .into_iter()
+            .map(|(manufacturer, score)| (manufacturer.id, score))
+            .collect();
+        let indexed: Vec<(u32, f64)> = Index::build(&manufacturers)
+            .rank(&query, &manufacturers)
+            .into_iter()
+            .map(|(manufacturer, score)| (manufacturer.id, score))
+            .collect();
+        prop_assert_eq!(indexed, scanned);
+    }
+}
@@ -0,0 +1,113 @@
+// Snapshots on disk and the differences between two of them
+mod common;
+
+use common::temp_dir;
+use manufacturers::snapshot::{self, Snapshot};
+use manufacturers::{Error, Manufacturer};
+
+fn manufacturer(id: u32, name: &str, country: &str) -> Manufacturer {
+    Manufacturer {
+        id,
+        name: Some(name.to_string()),
+        common_name: None,
+        country: Some(country.to_string()),
+        vehicle_types: Vec::new(),
+    }
+}
+
+fn ids(manufacturers: &[&Manufacturer]) -> Vec<u32> {
+    manufacturers
+        .iter()
+        .map(|manufacturer| manufacturer.id)
+        .collect()
+}
+
+#[test]
+fn diff_finds_added_removed_and_changed_manufacturers() {
+    let old = Snapshot::new(
+        "old",
+        vec![
+            manufacturer(1, "KEPT", "GERMANY"),
+            manufacturer(2, "REMOVED", "FRANCE"),
+            manufacturer(3, "MOVED", "JAPAN"),
+            manufacturer(4, "RENAMED", "ITALY"),
+        ],
+    );
+    // Records are matched by ID, not by their position in the list
+    let new = Snapshot::new(
+        "new",
+        vec![
+            manufacturer(5, "ADDED", "SPAIN"),
+            manufacturer(4, "RENAMED SPA", "ITALY"),
+            manufacturer(3, "MOVED", "CHINA"),
+            manufacturer(1, "KEPT", "GERMANY"),
+        ],
+    );
+
+    let diff = snapshot::diff(&old, &new);
+    assert!(!diff.is_empty());
+    assert_eq!(ids(&diff.added), [5]);
+    assert_eq!(ids(&diff.removed), [2]);
+    let changed: Vec<(u32, Option<&str>, Option<&str>)> = diff
+        .changed
+        .iter()
+        .map(|(before, after)| {
+            assert_eq!(before.id, after.id);
+            (
+                before.id,
+                before.country.as_deref(),
+                after.country.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        changed,
+        [
+            (3, Some("JAPAN"), Some("CHINA")),
+            (4, Some("ITALY"), Some("ITALY"))
+        ]
+    );
+    assert_eq!(diff.changed[1].1.name.as_deref(), Some("RENAMED SPA"));
+}
+
+#[test]
+fn identical_snapshots_have_no_diff() {
+    let manufacturers = vec![manufacturer(1, "KEPT", "GERMANY")];
+    let old = Snapshot::new("old", manufacturers.clone());
+    let new = Snapshot::new("new", manufacturers);
+    assert!(snapshot::diff(&old, &new).is_empty());
+
+    let empty = Snapshot::new("empty", Vec::new());
+    let diff = snapshot::diff(&empty, &old);
+    assert_eq!(ids(&diff.added), [1]);
+    assert!(diff.removed.is_empty() && diff.changed.is_empty());
+}
+
+#[test]
+fn snapshots_read_back_what_was_saved() {
+    let dir = temp_dir("snapshot-round-trip");
+    let path = dir.join("nested").join("snapshot.json");
+    let saved = Snapshot::new("test", vec![manufacturer(1, "KEPT", "GERMANY")]);
+    saved.save(&path).unwrap();
+
+    let loaded = Snapshot::load(&path).unwrap();
+    assert_eq!(loaded.source, "test");
+    assert_eq!(loaded.created_at, saved.created_at);
+    assert_eq!(loaded.manufacturers, saved.manufacturers);
+    assert!(snapshot::diff(&saved, &loaded).is_empty());
+}
+
+#[test]
+fn damaged_snapshots_are_decode_errors() {
+    let dir = temp_dir("snapshot-damaged");
+    let path = dir.join("snapshot.json");
+    std::fs::write(&path, "{\"created_at\": 1, \"source\": ").unwrap();
+
+    let error = Snapshot::load(&path).unwrap_err();
+    assert!(matches!(error, Error::Decode { .. }), "{}", error);
+    assert_eq!(error.exit_code(), 5);
+    assert!(error.to_string().contains(&path.display().to_string()));
+
+    let error = Snapshot::load(&dir.join("missing.json")).unwrap_err();
+    assert!(matches!(error, Error::Io { .. }), "{}", error);
+}
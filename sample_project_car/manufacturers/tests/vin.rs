@@ -0,0 +1,185 @@
+// Local VIN checks, none of them touch the network
+use manufacturers::alias::Aliases;
+use manufacturers::vin::{self, check_digit, VinError};
+use manufacturers::{Manufacturer, Vin};
+
+#[test]
+fn check_digits() {
+    let cases = [
+        ("1HGCM82633A004352", Some('3')),
+        ("11111111111111111", Some('1')),
+        // A remainder of 10 is written as X
+        ("1M8GDM9AXKP042788", Some('X')),
+        // Position 9 has weight 0, so its own value never counts
+        ("1M8GDM9A0KP042788", Some('X')),
+        ("1HGCM8263", None),
+        ("1HGCM82633A00435I", None),
+    ];
+    for (vin, expected) in cases {
+        assert_eq!(check_digit(vin), expected, "{}", vin);
+    }
+}
+
+#[test]
+fn letters_transliterate_like_their_digits() {
+    // A-H are 1-8, J-N 1-5, P 7, R 9 and S-Z 2-9
+    let cases = [
+        ('A', '1'),
+        ('B', '2'),
+        ('H', '8'),
+        ('J', '1'),
+        ('N', '5'),
+        ('P', '7'),
+        ('R', '9'),
+        ('S', '2'),
+        ('T', '3'),
+        ('Z', '9'),
+    ];
+    for (letter, digit) in cases {
+        let with_letter = format!("{}1111111111111111", letter);
+        let with_digit = format!("{}1111111111111111", digit);
+        assert_eq!(
+            check_digit(&with_letter),
+            check_digit(&with_digit),
+            "{} should count as {}",
+            letter,
+            digit
+        );
+    }
+}
+
+#[test]
+fn valid_vins_parse_and_split() {
+    let vin = Vin::parse(" 1hgcm82633a004352 ").unwrap();
+    assert_eq!(vin.as_str(), "1HGCM82633A004352");
+    assert_eq!(vin.wmi(), "1HG");
+    assert_eq!(vin.vds(), "CM8263");
+    assert_eq!(vin.vis(), "3A004352");
+    assert_eq!(vin.check_digit(), '3');
+    assert_eq!(vin.model_year_code(), '3');
+    assert!(Vin::parse("1M8GDM9AXKP042788").is_ok());
+}
+
+#[test]
+fn i_o_and_q_are_rejected() {
+    for (vin, position, found) in [
+        ("IHGCM82633A004352", 1, 'I'),
+        ("1HGCM8263OA004352", 10, 'O'),
+        ("1HGCM82633A00435Q", 17, 'Q'),
+        ("1HGCM82633A-04352", 12, '-'),
+    ] {
+        assert_eq!(
+            Vin::parse_structure(vin),
+            Err(VinError::InvalidChar { position, found }),
+            "{}",
+            vin
+        );
+    }
+}
+
+#[test]
+fn lengths_other_than_17_are_rejected() {
+    for (vin, length) in [
+        ("", 0),
+        ("1HGCM82633A00435", 16),
+        ("1HGCM82633A0043521", 18),
+        // Surrounding spaces do not count
+        ("  1HGCM8263  ", 9),
+    ] {
+        assert_eq!(Vin::parse(vin), Err(VinError::Length(length)), "{:?}", vin);
+    }
+}
+
+#[test]
+fn wrong_check_digits_are_rejected() {
+    assert_eq!(
+        Vin::parse("1HGCM82643A004352"),
+        Err(VinError::CheckDigit {
+            expected: '3',
+            found: '4',
+        })
+    );
+    // Only the structure is checked when asked to
+    assert!(Vin::parse_structure("1HGCM82643A004352").is_ok());
+}
+
+#[test]
+fn model_years_follow_the_30_year_cycle() {
+    // Position 7 is a digit before 2010 and a letter from 2010 on
+    let cases = [
+        ("1HGCM8263AA004352", Some(1980)),
+        ("1HGCM8263YA004352", Some(2000)),
+        ("1HGCM82631A004352", Some(2001)),
+        ("1HGCM82639A004352", Some(2009)),
+        ("1HGCM8A63AA004352", Some(2010)),
+        ("1HGCM8A63PA004352", Some(2023)),
+        ("1HGCM8A639A004352", Some(2039)),
+        // U, Z and 0 are never model year codes
+        ("1HGCM8263UA004352", None),
+        ("1HGCM8263ZA004352", None),
+        ("1HGCM82630A004352", None),
+    ];
+    for (code, year) in cases {
+        let vin = Vin::parse_structure(code).unwrap();
+        assert_eq!(vin.model_year(), year, "{}", code);
+    }
+}
+
+#[test]
+fn regions_come_from_the_first_character() {
+    let cases = [
+        ("AAV", Some("Africa")),
+        ("JHM", Some("Asia")),
+        ("RFB", Some("Asia")),
+        ("WBA", Some("Europe")),
+        ("1HG", Some("North America")),
+        ("5YJ", Some("North America")),
+        ("6T1", Some("Oceania")),
+        ("9BW", Some("South America")),
+        ("0AB", Some("South America")),
+        ("", None),
+        ("-AB", None),
+    ];
+    for (wmi, region) in cases {
+        assert_eq!(vin::region(wmi), region, "{:?}", wmi);
+    }
+}
+
+#[test]
+fn known_wmis_are_looked_up() {
+    let entry = vin::lookup_wmi("wba").unwrap();
+    assert_eq!((entry.make, entry.country), ("BMW", "Germany"));
+    assert!(vin::lookup_wmi("ZZZ").is_none());
+}
+
+#[test]
+fn wmi_makes_find_their_manufacturer_records() {
+    let manufacturer = |id: u32, name: &str| Manufacturer {
+        id,
+        name: Some(name.to_string()),
+        common_name: None,
+        country: None,
+        vehicle_types: Vec::new(),
+    };
+    let manufacturers = [
+        manufacturer(968, "BAYERISCHE MOTOREN WERKE AG"),
+        manufacturer(4108, "BMW OF NORTH AMERICA, LLC"),
+        manufacturer(1, "NIKIAS TRAILERS"),
+        manufacturer(2, "KIA CORPORATION"),
+    ];
+    let aliases = Aliases::builtin();
+    let ids = |wmi: &str| -> Vec<u32> {
+        vin::lookup_wmi(wmi)
+            .unwrap()
+            .find_in(&aliases, &manufacturers)
+            .iter()
+            .map(|manufacturer| manufacturer.id)
+            .collect()
+    };
+
+    // By alias and by name
+    assert_eq!(ids("WBA"), [968, 4108]);
+    // Whole words only
+    assert_eq!(ids("KNA"), [2]);
+    assert!(ids("JHM").is_empty());
+}
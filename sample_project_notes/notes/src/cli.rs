@@ -0,0 +1,539 @@
+// Definition of the command line, shared by the usage text,
+// the shell completion scripts and the man page
+
+use std::error::Error;
+use std::fmt;
+
+// What a positional argument or flag value completes to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    None,
+    NoteId,
+    Tag,
+    Shell,
+}
+
+pub struct Flag {
+    pub name: &'static str,
+    pub value: Value,
+    pub about: &'static str,
+}
+
+pub struct Command {
+    pub name: &'static str,
+    pub args: &'static str,
+    pub value: Value,
+    pub flags: &'static [Flag],
+    pub about: &'static str,
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "list",
+        args: "",
+        value: Value::None,
+        flags: &[
+            Flag {
+                name: "--starred",
+                value: Value::None,
+                about: "Only show starred notes",
+            },
+            Flag {
+                name: "--tag",
+                value: Value::Tag,
+                about: "Only show notes containing #tag",
+            },
+        ],
+        about: "List notes, pinned notes first",
+    },
+    Command {
+        name: "pin",
+        args: "<id> [position]",
+        value: Value::NoteId,
+        flags: &[],
+        about: "Pin a note, optionally at a position among the pinned notes",
+    },
+    Command {
+        name: "unpin",
+        args: "<id>",
+        value: Value::NoteId,
+        flags: &[],
+        about: "Unpin a note",
+    },
+    Command {
+        name: "star",
+        args: "<id>",
+        value: Value::NoteId,
+        flags: &[],
+        about: "Star a note",
+    },
+    Command {
+        name: "unstar",
+        args: "<id>",
+        value: Value::NoteId,
+        flags: &[],
+        about: "Remove the star from a note",
+    },
+    Command {
+        name: "dedupe",
+        args: "",
+        value: Value::None,
+        flags: &[Flag {
+            name: "--dry-run",
+            value: Value::None,
+            about: "Only report the duplicates",
+        }],
+        about: "Merge duplicate notes, keeping the earliest one",
+    },
+    Command {
+        name: "completions",
+        args: "<bash|zsh|fish>",
+        value: Value::Shell,
+        flags: &[],
+        about: "Print a shell completion script",
+    },
+    Command {
+        name: "man",
+        args: "",
+        value: Value::None,
+        flags: &[],
+        about: "Print the man page",
+    },
+    Command {
+        name: "help",
+        args: "",
+        value: Value::None,
+        flags: &[],
+        about: "Print this help",
+    },
+];
+
+// Words that are never stored as a note, hidden ones included
+pub fn is_command(word: &str) -> bool {
+    COMMANDS.iter().any(|command| command.name == word)
+        || ["--help", "-h", "__complete"].contains(&word)
+}
+
+// "list [--starred] [--tag <tag>]"
+fn synopsis(command: &Command) -> String {
+    let mut line = command.name.to_string();
+    if !command.args.is_empty() {
+        line.push(' ');
+        line.push_str(command.args);
+    }
+    for flag in command.flags {
+        match flag.value {
+            Value::None => line.push_str(&format!(" [{}]", flag.name)),
+            _ => line.push_str(&format!(" [{} <{}>]", flag.name, value_name(flag.value))),
+        }
+    }
+    line
+}
+
+fn value_name(value: Value) -> &'static str {
+    match value {
+        Value::None => "",
+        Value::NoteId => "id",
+        Value::Tag => "tag",
+        Value::Shell => "shell",
+    }
+}
+
+// Shell snippet producing the candidates for a value
+fn candidates(value: Value) -> &'static str {
+    match value {
+        Value::None => "",
+        Value::NoteId => "notes __complete ids",
+        Value::Tag => "notes __complete tags",
+        Value::Shell => "echo bash zsh fish",
+    }
+}
+
+// Wrong arguments, reported with the usage text instead of an error message
+#[derive(Debug)]
+pub struct UsageError;
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", usage())
+    }
+}
+
+impl Error for UsageError {}
+
+pub fn usage() -> String {
+    let mut usage = String::from("Usage: notes your_note_goes_here");
+    for command in COMMANDS {
+        usage.push_str(&format!("\n       notes {}", synopsis(command)));
+    }
+    usage
+}
+
+pub fn help() -> String {
+    let mut help = usage();
+    help.push_str("\n\nCommands:");
+    for command in COMMANDS {
+        help.push_str(&format!("\n  {:<12}{}", command.name, command.about));
+        for flag in command.flags {
+            help.push_str(&format!("\n    {:<10}{}", flag.name, flag.about));
+        }
+    }
+    help
+}
+
+pub fn bash() -> String {
+    let names: Vec<&str> = COMMANDS.iter().map(|command| command.name).collect();
+    let mut script = String::from(
+        "_notes() {
+    local cur prev
+    cur=\"${COMP_WORDS[COMP_CWORD]}\"
+    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"
+",
+    );
+    script.push_str(&format!(
+        "    if [ \"$COMP_CWORD\" -eq 1 ]; then
+        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))
+        return
+    fi
+    case \"${{COMP_WORDS[1]}}\" in
+",
+        names.join(" ")
+    ));
+
+    for command in COMMANDS {
+        if command.value == Value::None && command.flags.is_empty() {
+            continue;
+        }
+        script.push_str(&format!("        {})\n", command.name));
+        for flag in command
+            .flags
+            .iter()
+            .filter(|flag| flag.value != Value::None)
+        {
+            script.push_str(&format!(
+                "            if [ \"$prev\" = \"{}\" ]; then
+                COMPREPLY=($(compgen -W \"$({} | cut -f1)\" -- \"$cur\"))
+                return
+            fi
+",
+                flag.name,
+                candidates(flag.value)
+            ));
+        }
+        if command.value != Value::None {
+            script.push_str(&format!(
+                "            if [ \"$COMP_CWORD\" -eq 2 ]; then
+                COMPREPLY=($(compgen -W \"$({} | cut -f1)\" -- \"$cur\"))
+                return
+            fi
+",
+                candidates(command.value)
+            ));
+        }
+        if !command.flags.is_empty() {
+            let flags: Vec<&str> = command.flags.iter().map(|flag| flag.name).collect();
+            script.push_str(&format!(
+                "            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n",
+                flags.join(" ")
+            ));
+        }
+        script.push_str("            ;;\n");
+    }
+
+    script.push_str(
+        "    esac
+}
+complete -F _notes notes
+",
+    );
+    script
+}
+
+pub fn zsh() -> String {
+    let mut script = String::from(
+        "#compdef notes
+
+_notes_ids() {
+    local -a ids
+    ids=(${(f)\"$(notes __complete ids | sed -e 's/:/\\\\:/g' -e 's/\t/:/')\"})
+    _describe 'note id' ids
+}
+
+_notes_tags() {
+    local -a tags
+    tags=(${(f)\"$(notes __complete tags)\"})
+    _describe 'tag' tags
+}
+
+_notes() {
+    local -a commands
+    commands=(
+",
+    );
+    for command in COMMANDS {
+        script.push_str(&format!(
+            "        '{}:{}'\n",
+            command.name,
+            zsh_escape(command.about)
+        ));
+    }
+    script.push_str(
+        "    )
+    if (( CURRENT == 2 )); then
+        _describe 'command' commands
+        return
+    fi
+    shift words
+    (( CURRENT-- ))
+    case $words[1] in
+",
+    );
+
+    for command in COMMANDS {
+        if command.value == Value::None && command.flags.is_empty() {
+            continue;
+        }
+        let mut specs: Vec<String> = command
+            .flags
+            .iter()
+            .map(|flag| match flag.value {
+                Value::None => format!("'{}[{}]'", flag.name, zsh_escape(flag.about)),
+                value => format!(
+                    "'{}[{}]:{}:{}'",
+                    flag.name,
+                    zsh_escape(flag.about),
+                    value_name(value),
+                    zsh_action(value)
+                ),
+            })
+            .collect();
+        if command.value != Value::None {
+            specs.push(format!(
+                "'1:{}:{}'",
+                value_name(command.value),
+                zsh_action(command.value)
+            ));
+        }
+        script.push_str(&format!(
+            "        {})\n            _arguments {}\n            ;;\n",
+            command.name,
+            specs.join(" ")
+        ));
+    }
+
+    script.push_str(
+        "    esac
+}
+
+_notes \"$@\"
+",
+    );
+    script
+}
+
+// Text going between single quotes, zsh has no escapes inside them
+// so a quote closes them, adds an escaped quote and opens them again
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+}
+
+fn zsh_action(value: Value) -> &'static str {
+    match value {
+        Value::None => "",
+        Value::NoteId => "_notes_ids",
+        Value::Tag => "_notes_tags",
+        Value::Shell => "(bash zsh fish)",
+    }
+}
+
+// Text going between single quotes, fish allows \\ and \' inside them
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+pub fn fish() -> String {
+    let mut script = String::from("complete -c notes -f\n");
+    for command in COMMANDS {
+        script.push_str(&format!(
+            "complete -c notes -n __fish_use_subcommand -a {} -d '{}'\n",
+            command.name,
+            fish_escape(command.about)
+        ));
+    }
+    for command in COMMANDS {
+        let condition = format!("'__fish_seen_subcommand_from {}'", command.name);
+        if command.value != Value::None {
+            script.push_str(&format!(
+                "complete -c notes -n {} -a '({})'\n",
+                condition,
+                candidates(command.value)
+            ));
+        }
+        for flag in command.flags {
+            let long = flag.name.trim_start_matches("--");
+            match flag.value {
+                Value::None => script.push_str(&format!(
+                    "complete -c notes -n {} -l {} -d '{}'\n",
+                    condition,
+                    long,
+                    fish_escape(flag.about)
+                )),
+                value => script.push_str(&format!(
+                    "complete -c notes -n {} -l {} -r -a '({})' -d '{}'\n",
+                    condition,
+                    long,
+                    candidates(value),
+                    fish_escape(flag.about)
+                )),
+            }
+        }
+    }
+    script
+}
+
+// roff needs dashes escaped
+fn roff(text: &str) -> String {
+    text.replace('-', "\\-")
+}
+
+pub fn man_page() -> String {
+    let mut page = format!(
+        ".TH NOTES 1 \"\" \"notes {}\" \"User Commands\"
+.SH NAME
+notes \\- keep timestamped notes in a plain text file
+.SH SYNOPSIS
+.B notes
+.I your_note_goes_here
+",
+        env!("CARGO_PKG_VERSION")
+    );
+    for command in COMMANDS {
+        page.push_str(&format!(".br\n.B notes\n{}\n", roff(&synopsis(command))));
+    }
+
+    page.push_str(
+        ".SH DESCRIPTION
+Appends the note with the current time to notes.txt in the current directory.
+Words starting with # in a note are treated as tags.
+.SH COMMANDS
+",
+    );
+    for command in COMMANDS {
+        page.push_str(&format!(
+            ".TP\n.B {}\n{}\n",
+            roff(&synopsis(command)),
+            command.about
+        ));
+        for flag in command.flags {
+            page.push_str(&format!(
+                ".RS\n.TP\n.B {}\n{}\n.RE\n",
+                roff(flag.name),
+                flag.about
+            ));
+        }
+    }
+
+    page.push_str(
+        ".SH FILES
+.TP
+.B notes.txt
+The notes, each one preceded by a <!\\-\\- timestamp \\-\\-> line
+.TP
+.B notes.meta
+Pinned and starred note ids
+",
+    );
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::process::{Command as Process, Stdio};
+
+    // Syntax check of a script with `<shell> -n`, None when the shell is not installed
+    fn syntax_check(shell: &str, script: &str) -> Option<bool> {
+        let mut child = Process::new(shell)
+            .arg("-n")
+            .stdin(Stdio::piped())
+            .spawn()
+            .ok()?;
+        child.stdin.take()?.write_all(script.as_bytes()).unwrap();
+        Some(child.wait().unwrap().success())
+    }
+
+    // Single quotes of every line are balanced once the given escape is removed
+    fn quotes_balanced(script: &str, escaped: &str) -> bool {
+        script
+            .lines()
+            .all(|line| line.replace(escaped, "").matches('\'').count() % 2 == 0)
+    }
+
+    #[test]
+    fn commands_are_recognised() {
+        for command in COMMANDS {
+            assert!(is_command(command.name));
+        }
+        assert!(is_command("__complete"));
+        assert!(!is_command("buy milk"));
+    }
+
+    #[test]
+    fn bash_script_parses() {
+        let script = bash();
+        assert!(script.contains("complete -F _notes notes"));
+        for command in COMMANDS {
+            assert!(script.contains(command.name));
+        }
+        assert_eq!(syntax_check("bash", &script), Some(true));
+    }
+
+    #[test]
+    fn zsh_script_lists_every_command() {
+        let script = zsh();
+        assert!(script.starts_with("#compdef notes\n"));
+        for command in COMMANDS {
+            assert!(script.contains(&format!("'{}:{}'", command.name, command.about)));
+        }
+        assert!(script.contains("'--tag[Only show notes containing #tag]:tag:_notes_tags'"));
+        assert!(quotes_balanced(&script, "'\\''"));
+        assert_ne!(syntax_check("zsh", &script), Some(false));
+    }
+
+    #[test]
+    fn fish_script_lists_every_command() {
+        let script = fish();
+        for command in COMMANDS {
+            assert!(script.contains(&format!(
+                "complete -c notes -n __fish_use_subcommand -a {} -d '{}'\n",
+                command.name, command.about
+            )));
+        }
+        assert!(script.contains(
+            "complete -c notes -n '__fish_seen_subcommand_from pin' -a '(notes __complete ids)'\n"
+        ));
+        assert!(quotes_balanced(&script, "\\'"));
+        assert_ne!(syntax_check("fish", &script), Some(false));
+    }
+
+    #[test]
+    fn quotes_are_escaped() {
+        assert_eq!(zsh_escape("Don't"), "Don'\\''t");
+        assert_eq!(fish_escape("Don't \\"), "Don\\'t \\\\");
+        let zsh = format!("'{}'", zsh_escape("it's"));
+        assert!(quotes_balanced(&zsh, "'\\''"));
+        assert_eq!(syntax_check("bash", &format!("echo {}", zsh)), Some(true));
+    }
+
+    #[test]
+    fn man_page_documents_every_command() {
+        let page = man_page();
+        assert!(page.starts_with(".TH NOTES 1"));
+        for command in COMMANDS {
+            assert!(page.contains(&format!(".TP\n.B {}\n", roff(&synopsis(command)))));
+        }
+        assert!(page.contains(".B \\-\\-starred\n"));
+        assert!(!page.contains(" --"));
+    }
+}
@@ -0,0 +1,229 @@
+use chrono::{Duration, NaiveDateTime};
+
+use crate::notebook::{Note, TIMESTAMP_FORMAT};
+
+// Two notes at least this similar are considered duplicates
+const SIMILARITY_THRESHOLD: f64 = 0.9;
+
+// How far back a new note is compared against when adding
+const RECENT_HOURS: i64 = 24;
+
+// Collapse runs of whitespace and ignore case so "Buy  milk\n" matches "buy milk"
+fn normalise(body: &str) -> String {
+    body.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+// Edit distance between two strings, counted in chars
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Similarity between 0.0 (nothing in common) and 1.0 (same normalised text)
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalise(a), normalise(b));
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(&a, &b) as f64 / longest as f64
+}
+
+pub fn is_duplicate(a: &str, b: &str) -> bool {
+    similarity(a, b) >= SIMILARITY_THRESHOLD
+}
+
+// Most recent note added within RECENT_HOURS of now that looks like the body
+pub fn recent_duplicate<'a>(notes: &'a [Note], body: &str, now: NaiveDateTime) -> Option<&'a Note> {
+    notes.iter().rev().find(|note| {
+        let recent = NaiveDateTime::parse_from_str(&note.timestamp, TIMESTAMP_FORMAT)
+            .map(|added| now - added <= Duration::hours(RECENT_HOURS))
+            .unwrap_or(false);
+        recent && is_duplicate(&note.body, body)
+    })
+}
+
+// Outcome of merging a notebook
+pub struct Merge {
+    // Remaining notes renumbered in file order
+    pub notes: Vec<Note>,
+    // mapping[old id - 1] is the new id of the note it ended up in
+    pub mapping: Vec<usize>,
+    // (duplicate id, id of the note it was merged into), both old ids
+    pub duplicates: Vec<(usize, usize)>,
+}
+
+// Merge duplicate notes, keeping the one with the earliest timestamp
+pub fn merge(notes: &[Note]) -> Merge {
+    // Visit the notes oldest first so the earliest copy is the one kept
+    let mut by_time: Vec<&Note> = notes.iter().collect();
+    by_time.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+    // keeper[old id - 1] is the id of the note it gets merged into
+    let mut keeper = vec![0; notes.len()];
+    let mut kept: Vec<&Note> = Vec::new();
+    for note in by_time {
+        match kept
+            .iter()
+            .find(|kept| is_duplicate(&kept.body, &note.body))
+        {
+            Some(original) => keeper[note.id - 1] = original.id,
+            None => {
+                keeper[note.id - 1] = note.id;
+                kept.push(note);
+            }
+        }
+    }
+
+    // Renumber the kept notes in their original file order
+    let mut merged = Vec::new();
+    let mut new_ids = vec![0; notes.len()];
+    for note in notes.iter().filter(|note| keeper[note.id - 1] == note.id) {
+        merged.push(Note {
+            id: merged.len() + 1,
+            ..note.clone()
+        });
+        new_ids[note.id - 1] = merged.len();
+    }
+    let mapping = keeper.iter().map(|&id| new_ids[id - 1]).collect();
+    let duplicates = notes
+        .iter()
+        .filter(|note| keeper[note.id - 1] != note.id)
+        .map(|note| (note.id, keeper[note.id - 1]))
+        .collect();
+
+    Merge {
+        notes: merged,
+        mapping,
+        duplicates,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: usize, timestamp: &str, body: &str) -> Note {
+        Note {
+            id,
+            timestamp: timestamp.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    fn at(timestamp: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn similarity_threshold() {
+        // Case and whitespace do not count
+        assert!(is_duplicate("Buy  milk\n", "buy milk"));
+        // One edit in ten chars is exactly 0.9
+        assert!(is_duplicate("abcdefghij", "abcdefghiX"));
+        // Two edits in ten chars is 0.8
+        assert!(!is_duplicate("abcdefghij", "abcdefghXY"));
+        assert!(!is_duplicate("buy milk", "call mom"));
+    }
+
+    #[test]
+    fn recent_duplicate_looks_back_24_hours() {
+        let notes = vec![
+            note(1, "2022-11-14 10:00:00", "buy milk"),
+            note(2, "2022-11-15 09:00:00", "call mom"),
+        ];
+        let now = at("2022-11-15 10:00:00");
+        assert_eq!(
+            recent_duplicate(&notes, "Call  Mom", now).map(|note| note.id),
+            Some(2)
+        );
+        // Exactly 24 hours ago still counts, a second more does not
+        assert_eq!(
+            recent_duplicate(&notes, "buy milk", now).map(|note| note.id),
+            Some(1)
+        );
+        let later = at("2022-11-15 10:00:01");
+        assert!(recent_duplicate(&notes, "buy milk", later).is_none());
+        assert!(recent_duplicate(&notes, "walk the dog", now).is_none());
+    }
+
+    #[test]
+    fn recent_duplicate_prefers_the_latest_note() {
+        let notes = vec![
+            note(1, "2022-11-15 08:00:00", "buy milk"),
+            note(2, "2022-11-15 09:00:00", "buy milk"),
+        ];
+        let found = recent_duplicate(&notes, "buy milk", at("2022-11-15 10:00:00"));
+        assert_eq!(found.map(|note| note.id), Some(2));
+    }
+
+    #[test]
+    fn merge_keeps_the_earliest_copy() {
+        // The later copy comes first in the file, as after a hand edit
+        let notes = vec![
+            note(1, "2022-11-15 12:00:00", "Buy milk"),
+            note(2, "2022-11-15 09:00:00", "call mom"),
+            note(3, "2022-11-15 10:00:00", "buy milk"),
+            note(4, "2022-11-15 11:00:00", "walk the dog"),
+            note(5, "2022-11-15 13:00:00", "Call mom"),
+        ];
+        let merge = merge(&notes);
+
+        let kept: Vec<(usize, &str, &str)> = merge
+            .notes
+            .iter()
+            .map(|note| (note.id, note.timestamp.as_str(), note.body.as_str()))
+            .collect();
+        assert_eq!(
+            kept,
+            vec![
+                (1, "2022-11-15 09:00:00", "call mom"),
+                (2, "2022-11-15 10:00:00", "buy milk"),
+                (3, "2022-11-15 11:00:00", "walk the dog"),
+            ]
+        );
+        assert_eq!(merge.mapping, vec![2, 1, 2, 3, 1]);
+        assert_eq!(merge.duplicates, vec![(1, 3), (5, 2)]);
+    }
+
+    #[test]
+    fn merge_moves_pins_and_stars_to_the_kept_note() {
+        let notes = vec![
+            note(1, "2022-11-15 09:00:00", "buy milk"),
+            note(2, "2022-11-15 10:00:00", "call mom"),
+            note(3, "2022-11-15 11:00:00", "buy milk"),
+        ];
+        let merge = merge(&notes);
+        let mut meta = crate::meta::Meta {
+            pinned: vec![3, 2],
+            starred: vec![1, 3],
+        };
+        meta.remap(&merge.mapping);
+        assert_eq!(meta.pinned, vec![1, 2]);
+        assert_eq!(meta.starred, vec![1]);
+    }
+
+    #[test]
+    fn merge_without_duplicates_changes_nothing() {
+        let notes = vec![
+            note(1, "2022-11-15 09:00:00", "buy milk"),
+            note(2, "2022-11-15 10:00:00", "call mom"),
+        ];
+        let merge = merge(&notes);
+        assert_eq!(merge.notes.len(), 2);
+        assert_eq!(merge.mapping, vec![1, 2]);
+        assert!(merge.duplicates.is_empty());
+    }
+}
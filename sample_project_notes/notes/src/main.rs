@@ -1,42 +1,239 @@
 // Run cargo watch with
-// > cargo-watch -qc -x 'run -- "YOUR_NOTE"' -i "notes.txt" -x clippy
+// > cargo-watch -qc -x 'run -- "YOUR_NOTE"' -i "notes.txt" -i "notes.meta" -x clippy
 // -i ignores to keep loading the notes.txt indefinitely by cargo-watch
+// Other commands: notes list, notes pin 2, notes star 3, notes dedupe, notes help
+// Install completions with e.g. > notes completions bash > /etc/bash_completion.d/notes
 
 #![deny(clippy::all)]
 
+mod cli;
+mod dedupe;
+mod meta;
+mod notebook;
+
+use std::collections::BTreeSet;
 use std::env;
-use std::fs::OpenOptions;
-use std::io::prelude::*;
+use std::error::Error;
+use std::process::ExitCode;
+
+use meta::Meta;
+use notebook::Note;
+
+// Files where the notes and their pin/star flags are stored
+const NOTES_FILE: &str = "notes.txt";
+const META_FILE: &str = "notes.meta";
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
     // Collect input args
     let args: Vec<String> = env::args().collect();
 
-    if args.len() != 2 {
-        Err("Usage: notes your_note_goes_here")?;
-        std::process::exit(1);
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        // The usage text is printed as it is, it says what went wrong by itself
+        Err(e) if e.is::<cli::UsageError>() => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.len() < 2 {
+        Err(cli::UsageError)?;
+    }
+
+    // Dispatch on the first argument, anything else is a note to store
+    match (args[1].as_str(), &args[2..]) {
+        ("list", flags) => list(flags),
+        ("pin", [id]) => with_meta(id, |meta, id| {
+            meta.pin(id, None);
+            Ok(())
+        }),
+        ("pin", [id, position]) => {
+            let position: usize = position
+                .parse()
+                .map_err(|_| format!("Invalid position: {}", position))?;
+            with_meta(id, |meta, id| {
+                meta.pin(id, Some(position));
+                Ok(())
+            })
+        }
+        ("unpin", [id]) => with_meta(id, |meta, id| {
+            if !meta.unpin(id) {
+                Err(format!("Note {} is not pinned", id))?;
+            }
+            Ok(())
+        }),
+        ("star", [id]) => with_meta(id, |meta, id| {
+            meta.star(id);
+            Ok(())
+        }),
+        ("unstar", [id]) => with_meta(id, |meta, id| {
+            if !meta.unstar(id) {
+                Err(format!("Note {} is not starred", id))?;
+            }
+            Ok(())
+        }),
+        ("dedupe", []) => dedupe(false),
+        ("dedupe", [flag]) if flag == "--dry-run" => dedupe(true),
+        ("completions", [shell]) => {
+            match shell.as_str() {
+                "bash" => print!("{}", cli::bash()),
+                "zsh" => print!("{}", cli::zsh()),
+                "fish" => print!("{}", cli::fish()),
+                _ => Err(format!("Unsupported shell: {}", shell))?,
+            }
+            Ok(())
+        }
+        ("man", []) => {
+            print!("{}", cli::man_page());
+            Ok(())
+        }
+        ("help" | "--help" | "-h", []) => {
+            println!("{}", cli::help());
+            Ok(())
+        }
+        // Hidden command used by the completion scripts
+        ("__complete", [kind]) => complete(kind),
+        // A command with a missing or extra argument must not be stored as a note
+        (command, _) if cli::is_command(command) => Err(cli::UsageError.into()),
+        (note, []) => add(note),
+        _ => Err(cli::UsageError.into()),
+    }
+}
+
+// Store a new note with the current time
+// Scripts tend to add the same note twice, so warn about recent look-alikes
+fn add(note: &str) -> Result<(), Box<dyn Error>> {
+    let now = chrono::Local::now().naive_local();
+    let notes = notebook::read_notes(NOTES_FILE)?;
+    if let Some(duplicate) = dedupe::recent_duplicate(&notes, note, now) {
+        eprintln!(
+            "Warning: this looks like note #{} added at {}, run `notes dedupe` to merge duplicates",
+            duplicate.id, duplicate.timestamp
+        );
+    }
+
+    let now = now.format(notebook::TIMESTAMP_FORMAT).to_string();
+    notebook::append_note(NOTES_FILE, &now, note)?;
+    Ok(())
+}
+
+// Merge duplicate notes and carry their pins and stars over
+fn dedupe(dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let notes = notebook::read_notes(NOTES_FILE)?;
+    let merge = dedupe::merge(&notes);
+
+    if merge.duplicates.is_empty() {
+        println!("No duplicate notes found");
+        return Ok(());
+    }
+    for (duplicate, original) in &merge.duplicates {
+        let summary = notes[duplicate - 1].summary();
+        println!(
+            "#{} is a duplicate of #{}: {}",
+            duplicate, original, summary
+        );
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    let mut meta = Meta::load(META_FILE)?;
+    meta.remap(&merge.mapping);
+    notebook::write_notes(NOTES_FILE, &merge.notes)?;
+    meta.save(META_FILE)?;
+    println!("Merged {} duplicate notes", merge.duplicates.len());
+    Ok(())
+}
+
+// Print every note, pinned ones first
+fn list(flags: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut only_starred = false;
+    let mut tag = None;
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--starred" => only_starred = true,
+            "--tag" => tag = Some(flags.next().ok_or(cli::UsageError)?.to_lowercase()),
+            _ => Err(cli::UsageError)?,
+        }
     }
 
-    // Grab note
-    let note = args[1].clone();
-    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let notes = notebook::read_notes(NOTES_FILE)?;
+    let meta = Meta::load(META_FILE)?;
+
+    let ids: Vec<usize> = notes.iter().map(|note| note.id).collect();
+    for id in meta.order(&ids) {
+        let note = &notes[id - 1];
+        if only_starred && !meta.is_starred(id) {
+            continue;
+        }
+        if let Some(tag) = &tag {
+            if !note.tags().contains(tag) {
+                continue;
+            }
+        }
+        println!("{}", describe(note, &meta));
+        println!("{}\n", note.body);
+    }
+    Ok(())
+}
 
-    // Write in a file
-    let mut file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .append(true)
-        .open("notes.txt")
-        .unwrap();
+// Header line shown above each note in the listing
+fn describe(note: &Note, meta: &Meta) -> String {
+    let mut header = format!("#{}", note.id);
+    if meta.is_pinned(note.id) {
+        header.push_str(" [pinned]");
+    }
+    if meta.is_starred(note.id) {
+        header.push_str(" [starred]");
+    }
+    format!("{} {}", header, note.timestamp)
+}
 
-    // Write the current time to the file
-    file.write_all(b"<!-- ")?;
-    file.write_all(now.as_bytes())?;
-    file.write_all(b" -->\n")?;
+// Print completion candidates, note ids come with their summary after a tab
+fn complete(kind: &str) -> Result<(), Box<dyn Error>> {
+    let notes = notebook::read_notes(NOTES_FILE)?;
+    match kind {
+        "ids" => {
+            for note in &notes {
+                println!("{}\t{}", note.id, note.summary());
+            }
+        }
+        "tags" => {
+            let tags: BTreeSet<String> = notes.iter().flat_map(|note| note.tags()).collect();
+            for tag in tags {
+                println!("{}", tag);
+            }
+        }
+        _ => Err(format!("Unknown completion: {}", kind))?,
+    }
+    Ok(())
+}
 
-    // Store the note into the file
-    file.write_all(note.as_bytes())?;
-    file.write_all(b"\n\n")?;
+// Make sure the id points to an existing note
+fn parse_id(id: &str) -> Result<usize, Box<dyn Error>> {
+    let id: usize = id.parse().map_err(|_| format!("Invalid note id: {}", id))?;
+    let count = notebook::read_notes(NOTES_FILE)?.len();
+    if id == 0 || id > count {
+        Err(format!("No note with id {}", id))?;
+    }
+    Ok(id)
+}
 
+// Load the metadata, apply a change for an existing note and save it back
+fn with_meta<F>(id: &str, change: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&mut Meta, usize) -> Result<(), Box<dyn Error>>,
+{
+    let id = parse_id(id)?;
+    let mut meta = Meta::load(META_FILE)?;
+    change(&mut meta, id)?;
+    meta.save(META_FILE)?;
     Ok(())
 }
@@ -0,0 +1,207 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, ErrorKind};
+
+// Pin and star flags live in a sidecar file so notes.txt stays plain text
+// One "pinned <id>" or "starred <id>" per line, pinned lines keep their order
+#[derive(Debug, Default)]
+pub struct Meta {
+    pub pinned: Vec<usize>,
+    pub starred: Vec<usize>,
+}
+
+impl Meta {
+    // Load the metadata, an absent file means nothing is pinned or starred
+    pub fn load(path: &str) -> Result<Meta, Box<dyn Error>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Meta::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut meta = Meta::default();
+        for line in content.lines() {
+            match line.trim().split_once(' ') {
+                Some(("pinned", id)) => meta.pinned.push(id.trim().parse()?),
+                Some(("starred", id)) => meta.starred.push(id.trim().parse()?),
+                // Unknown lines are ignored so newer files still load
+                _ => {}
+            }
+        }
+        Ok(meta)
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut content = String::new();
+        for id in &self.pinned {
+            content.push_str(&format!("pinned {}\n", id));
+        }
+        for id in &self.starred {
+            content.push_str(&format!("starred {}\n", id));
+        }
+        fs::write(path, content)
+    }
+
+    // Pin a note, optionally at a 1-based position among the pinned notes
+    // Pinning an already pinned note moves it
+    pub fn pin(&mut self, id: usize, position: Option<usize>) {
+        self.pinned.retain(|&pinned| pinned != id);
+        match position {
+            Some(position) => {
+                let index = position.saturating_sub(1).min(self.pinned.len());
+                self.pinned.insert(index, id);
+            }
+            None => self.pinned.push(id),
+        }
+    }
+
+    pub fn unpin(&mut self, id: usize) -> bool {
+        let before = self.pinned.len();
+        self.pinned.retain(|&pinned| pinned != id);
+        before != self.pinned.len()
+    }
+
+    pub fn star(&mut self, id: usize) {
+        if !self.is_starred(id) {
+            self.starred.push(id);
+        }
+    }
+
+    pub fn unstar(&mut self, id: usize) -> bool {
+        let before = self.starred.len();
+        self.starred.retain(|&starred| starred != id);
+        before != self.starred.len()
+    }
+
+    pub fn is_pinned(&self, id: usize) -> bool {
+        self.pinned.contains(&id)
+    }
+
+    pub fn is_starred(&self, id: usize) -> bool {
+        self.starred.contains(&id)
+    }
+
+    // Point the flags at new ids after notes were merged, mapping[old id - 1] is the new id
+    pub fn remap(&mut self, mapping: &[usize]) {
+        let remap = |ids: &[usize]| {
+            let mut remapped: Vec<usize> = Vec::new();
+            for &id in ids {
+                // Ids start at 1, a hand-edited 0 points at no note
+                let new_id = id.checked_sub(1).and_then(|index| mapping.get(index));
+                if let Some(&new_id) = new_id {
+                    if !remapped.contains(&new_id) {
+                        remapped.push(new_id);
+                    }
+                }
+            }
+            remapped
+        };
+        self.pinned = remap(&self.pinned);
+        self.starred = remap(&self.starred);
+    }
+
+    // Listing order: pinned notes first in their custom order, then the rest as given
+    pub fn order(&self, ids: &[usize]) -> Vec<usize> {
+        let pinned = self.pinned.iter().filter(|id| ids.contains(id)).copied();
+        let rest = ids.iter().filter(|id| !self.is_pinned(**id)).copied();
+        pinned.chain(rest).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn order_lists_pinned_notes_first_in_their_order() {
+        let meta = Meta {
+            pinned: vec![4, 2],
+            starred: vec![1],
+        };
+        assert_eq!(meta.order(&[1, 2, 3, 4, 5]), vec![4, 2, 1, 3, 5]);
+    }
+
+    #[test]
+    fn order_skips_pins_of_missing_notes() {
+        let meta = Meta {
+            pinned: vec![9, 2],
+            starred: Vec::new(),
+        };
+        assert_eq!(meta.order(&[1, 2, 3]), vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn pin_at_position() {
+        let mut meta = Meta::default();
+        meta.pin(1, None);
+        meta.pin(2, None);
+        meta.pin(3, Some(1));
+        assert_eq!(meta.pinned, vec![3, 1, 2]);
+        // Past the end appends, 0 counts as the first position
+        meta.pin(4, Some(10));
+        meta.pin(5, Some(0));
+        assert_eq!(meta.pinned, vec![5, 3, 1, 2, 4]);
+    }
+
+    #[test]
+    fn pinning_again_moves_the_note() {
+        let mut meta = Meta::default();
+        meta.pin(1, None);
+        meta.pin(2, None);
+        meta.pin(3, None);
+        meta.pin(3, Some(2));
+        assert_eq!(meta.pinned, vec![1, 3, 2]);
+        meta.pin(1, None);
+        assert_eq!(meta.pinned, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn unpin_and_unstar_tell_whether_anything_changed() {
+        let mut meta = Meta::default();
+        meta.pin(1, None);
+        meta.star(2);
+        meta.star(2);
+        assert_eq!(meta.starred, vec![2]);
+        assert!(meta.unpin(1));
+        assert!(!meta.unpin(1));
+        assert!(meta.unstar(2));
+        assert!(!meta.unstar(2));
+    }
+
+    #[test]
+    fn remap_follows_merged_notes() {
+        let mut meta = Meta {
+            pinned: vec![3, 1, 0, 7],
+            starred: vec![2, 4],
+        };
+        // 2 was merged into 1 and 4 into 3, 7 is past the notebook
+        meta.remap(&[1, 1, 2, 2, 3]);
+        assert_eq!(meta.pinned, vec![2, 1]);
+        assert_eq!(meta.starred, vec![1, 2]);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = env::temp_dir().join(format!("notes-meta-{}", process::id()));
+        let path = path.to_str().unwrap();
+        let mut meta = Meta::default();
+        meta.pin(3, None);
+        meta.pin(1, None);
+        meta.star(2);
+        meta.save(path).unwrap();
+
+        let loaded = Meta::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded.pinned, vec![3, 1]);
+        assert_eq!(loaded.starred, vec![2]);
+    }
+
+    #[test]
+    fn load_of_a_missing_file_is_empty() {
+        let meta = Meta::load("does-not-exist.meta").unwrap();
+        assert!(meta.pinned.is_empty());
+        assert!(meta.starred.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use std::fs::{self, OpenOptions};
+use std::io::prelude::*;
+use std::io::{self, ErrorKind};
+
+// Format of the timestamp in each note header
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// A single note read back from notes.txt
+// The id is the 1-based position of the note in the file
+#[derive(Debug, Clone)]
+pub struct Note {
+    pub id: usize,
+    pub timestamp: String,
+    pub body: String,
+}
+
+impl Note {
+    // Words starting with '#' in the body, e.g. "#work", lowercased
+    pub fn tags(&self) -> Vec<String> {
+        self.body
+            .split_whitespace()
+            .filter_map(|word| word.strip_prefix('#'))
+            .map(|tag| tag.trim_end_matches(|c: char| !c.is_alphanumeric()))
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_lowercase())
+            .collect()
+    }
+
+    // First line of the body, shortened for one-line displays
+    pub fn summary(&self) -> String {
+        let line = self.body.lines().next().unwrap_or_default();
+        if line.chars().count() > 40 {
+            format!("{}...", line.chars().take(40).collect::<String>())
+        } else {
+            line.to_string()
+        }
+    }
+}
+
+// Pull the timestamp out of a "<!-- 2022-11-15 23:12:15 -->" header line
+// rfind skips broken headers such as "<!-- <!-- 2022-11-15 23:10:09 -->"
+fn header_timestamp(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if !line.starts_with("<!--") || !line.ends_with("-->") {
+        return None;
+    }
+    let inner = &line[..line.len() - 3];
+    let start = inner.rfind("<!--")? + 4;
+    Some(inner[start..].trim())
+}
+
+// Read every note in the file, an absent file is just an empty notebook
+pub fn read_notes(path: &str) -> io::Result<Vec<Note>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    // Split the file into (timestamp, lines) blocks
+    let mut blocks: Vec<(String, Vec<&str>)> = Vec::new();
+    for line in content.lines() {
+        match header_timestamp(line) {
+            Some(timestamp) => blocks.push((timestamp.to_string(), Vec::new())),
+            None => {
+                if let Some((_, lines)) = blocks.last_mut() {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+
+    // Headers without a body come from interrupted writes, drop them
+    let notes = blocks
+        .into_iter()
+        .map(|(timestamp, lines)| (timestamp, lines.join("\n").trim().to_string()))
+        .filter(|(_, body)| !body.is_empty())
+        .enumerate()
+        .map(|(index, (timestamp, body))| Note {
+            id: index + 1,
+            timestamp,
+            body,
+        })
+        .collect();
+
+    Ok(notes)
+}
+
+// Append a note in the same layout main has always written
+pub fn append_note(path: &str, timestamp: &str, body: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    // Write the current time to the file
+    file.write_all(b"<!-- ")?;
+    file.write_all(timestamp.as_bytes())?;
+    file.write_all(b" -->\n")?;
+
+    // Store the note into the file
+    file.write_all(body.as_bytes())?;
+    file.write_all(b"\n\n")?;
+
+    Ok(())
+}
+
+// Rewrite the whole file with the given notes
+// Goes through a temporary file so an interrupted write keeps the old notes
+pub fn write_notes(path: &str, notes: &[Note]) -> io::Result<()> {
+    let tmp = format!("{}.tmp", path);
+    let _ = fs::remove_file(&tmp);
+    for note in notes {
+        append_note(&tmp, &note.timestamp, &note.body)?;
+    }
+    if notes.is_empty() {
+        fs::write(&tmp, "")?;
+    }
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    // Write `content` to a file of its own and read it back
+    fn read(name: &str, content: &str) -> Vec<Note> {
+        let path = env::temp_dir().join(format!("notes-{}-{}.txt", name, process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, content).unwrap();
+        let notes = read_notes(path).unwrap();
+        fs::remove_file(path).unwrap();
+        notes
+    }
+
+    #[test]
+    fn reads_notes_in_file_order() {
+        let notes = read(
+            "order",
+            "<!-- 2022-11-15 23:11:24 -->\nfirst\n\n<!-- 2022-11-15 23:12:15 -->\nsecond\nline\n\n",
+        );
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].id, 1);
+        assert_eq!(notes[0].timestamp, "2022-11-15 23:11:24");
+        assert_eq!(notes[0].body, "first");
+        assert_eq!(notes[1].id, 2);
+        assert_eq!(notes[1].body, "second\nline");
+    }
+
+    #[test]
+    fn header_only_blocks_are_dropped() {
+        let notes = read(
+            "headers",
+            "<!-- 2022-11-15 23:10:09 -->\n\n<!-- 2022-11-15 23:11:24 -->\nkept\n\n<!-- 2022-11-15 23:12:00 -->\n",
+        );
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].id, 1);
+        assert_eq!(notes[0].timestamp, "2022-11-15 23:11:24");
+    }
+
+    #[test]
+    fn malformed_header_keeps_its_timestamp() {
+        let notes = read("malformed", "<!-- <!-- 2022-11-15 23:10:09 -->\nbody\n\n");
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].timestamp, "2022-11-15 23:10:09");
+        assert_eq!(notes[0].body, "body");
+    }
+
+    fn note(body: &str) -> Note {
+        Note {
+            id: 1,
+            timestamp: "2022-11-15 23:11:24".to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn tags_are_lowercased_without_trailing_punctuation() {
+        let tagged = note("Call #Bob about #work, #urgent!\n#home/#");
+        assert_eq!(tagged.tags(), vec!["bob", "work", "urgent", "home"]);
+        assert!(note("no tags # here").tags().is_empty());
+    }
+
+    #[test]
+    fn summary_is_the_shortened_first_line() {
+        assert_eq!(note("first\nsecond").summary(), "first");
+        let long = "a".repeat(50);
+        assert_eq!(note(&long).summary(), format!("{}...", "a".repeat(40)));
+    }
+
+    #[test]
+    fn missing_file_is_an_empty_notebook() {
+        assert!(read_notes("does-not-exist.txt").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+// The notes binary end to end, each test in its own directory since notes.txt is relative
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Output};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("notes-cli-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn notes(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_notes"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn wrong_arguments_print_the_usage() {
+    let dir = temp_dir("usage");
+    for args in [
+        &[][..],
+        &["pin"],
+        &["list", "--tag"],
+        &["list", "--all"],
+        &["a", "b"],
+    ] {
+        let output = notes(&dir, args);
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        let stderr = stderr(&output);
+        assert!(
+            stderr.starts_with("Usage: notes your_note_goes_here\n       notes list"),
+            "{:?}: {}",
+            args,
+            stderr
+        );
+        assert!(!stderr.contains("\\n"), "{}", stderr);
+    }
+    // Nothing was stored as a note
+    assert!(!dir.join("notes.txt").exists());
+}
+
+#[test]
+fn errors_print_their_message() {
+    let dir = temp_dir("errors");
+    let output = notes(&dir, &["star", "x"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "Error: Invalid note id: x\n");
+
+    let output = notes(&dir, &["pin", "1", "x"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "Error: Invalid position: x\n");
+}